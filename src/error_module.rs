@@ -0,0 +1,172 @@
+#![allow(dead_code)]
+pub mod error_module {
+    use std::path::{Path, PathBuf};
+    use image::{ImageError, ImageFormat};
+    use thiserror::Error;
+    use crate::screenshots_module::screenshot_module::ClipboardMode;
+    use crate::settings_module::settings_module::SETTINGS_VERSION;
+
+    /// Every error of Rusty Capture, grouped by what failed
+    #[derive(Error, Debug)]
+    pub enum RustyCaptureError {
+        #[error(transparent)]
+        Capture(#[from] CaptureError),
+        #[error(transparent)]
+        Edit(#[from] EditError),
+        #[error("Could not access {}: {source}", path.display())]
+        Io { path: PathBuf, source: std::io::Error },
+        #[error("{} is not a folder", .0.display())]
+        NotADirectory(PathBuf),
+        #[error(transparent)]
+        Encoding(#[from] EncodingError),
+        #[error(transparent)]
+        Clipboard(#[from] ClipboardError),
+        #[error(transparent)]
+        Hotkey(#[from] HotkeyError),
+        #[error(transparent)]
+        Settings(#[from] SettingsError),
+        #[error(transparent)]
+        History(#[from] HistoryError),
+    }
+
+    #[derive(Error, Debug)]
+    pub enum CaptureError {
+        #[error("Could not capture screen {screen}: {source}")]
+        Screen { screen: u32, source: Box<dyn std::error::Error + Send + Sync> },
+        #[error("Screen {screen} gave a {width}x{height} image with {len} bytes of pixels")]
+        WrongSize { screen: u32, width: u32, height: u32, len: usize },
+        #[error("Could not list the screens: {0}")]
+        ScreenList(#[source] Box<dyn std::error::Error + Send + Sync>),
+        #[error("No screen was detected")]
+        NoScreens,
+        #[error("Screen {screen} is not connected, there are {count} screens")]
+        MissingScreen { screen: usize, count: usize },
+    }
+
+    #[derive(Error, Debug)]
+    pub enum EditError {
+        #[error("The {width}x{height} area at ({x}, {y}) does not fit in the {image_width}x{image_height} image")]
+        AreaOutOfBounds { x: u32, y: u32, width: i32, height: i32, image_width: u32, image_height: u32 },
+        #[error("The image cannot be resized to {width}x{height}")]
+        InvalidSize { width: u32, height: u32 },
+        #[error("The image would be {width}x{height}, more than the {max} megapixels it can have")]
+        TooLarge { width: u64, height: u64, max: u64 },
+        #[error("The selected area is empty")]
+        EmptySelection,
+        #[error("The image has nothing but uniform borders, there is nothing to trim")]
+        NothingToTrim,
+    }
+
+    #[derive(Error, Debug)]
+    pub enum EncodingError {
+        #[error("{0:?} is not a supported output format")]
+        UnsupportedFormat(ImageFormat),
+        #[error("Could not open the image {}: {source}", path.display())]
+        Decode { path: PathBuf, source: ImageError },
+        #[error("Could not read the image data: {0}")]
+        DecodeBytes(ImageError),
+        #[error("Could not save {} as {format:?}: {source}", path.display())]
+        Encode { path: PathBuf, format: ImageFormat, source: ImageError },
+        #[error("Could not encode the image as {format:?}: {source}")]
+        EncodeBytes { format: ImageFormat, source: ImageError },
+        #[error("{} is not valid JSON: {source}", path.display())]
+        Json { path: PathBuf, source: serde_json::Error },
+        #[error("Could not write the PNG file with its metadata: {0}")]
+        Png(#[from] png::EncodingError),
+    }
+
+    #[derive(Error, Debug)]
+    pub enum ClipboardError {
+        #[error("The clipboard is not available: {0}")]
+        Unavailable(arboard::Error),
+        #[error("Could not copy the screenshot as {mode}: {source}")]
+        Copy { mode: ClipboardMode, source: arboard::Error },
+        #[error("Save the screenshot first to copy it as {0}")]
+        NotSaved(ClipboardMode),
+        #[error("The clipboard does not contain an image")]
+        NoImage,
+    }
+
+    #[derive(Error, Debug)]
+    pub enum HotkeyError {
+        #[error("\"{0}\" is not a valid hotkey, use something like Ctrl+Shift+F4")]
+        ParseError(String),
+        #[error("\"{0}\" is not a valid key")]
+        KeyError(String),
+        #[error("{0} is already taken by another application")]
+        Unavailable(String),
+        #[error("The global hotkeys are not available: {0}")]
+        Manager(global_hotkey::Error),
+        #[error("Could not register {hotkey}: {source}")]
+        Register { hotkey: String, source: global_hotkey::Error },
+    }
+
+    #[derive(Error, Debug)]
+    pub enum SettingsError {
+        #[error("Invalid settings:\n{0}")]
+        InvalidSettings(String),
+        #[error("{} is not a JSON object", .0.display())]
+        FormatError(PathBuf),
+        #[error("{} has version {version}, this build only supports up to {SETTINGS_VERSION}", path.display())]
+        VersionError { path: PathBuf, version: u64 },
+        #[error("The settings file {} has no name", .0.display())]
+        FileError(PathBuf),
+        #[error("Invalid hotkey for {0}: \"{1}\"")]
+        HotkeyError(String, String),
+        #[error("{0} and {1} have the same hotkey \"{2}\"")]
+        DuplicateHotkey(String, String, String),
+        #[error("Unknown option \"{0}\", settings are set with --<setting> <value>, see --print-config")]
+        UnknownOption(String),
+        #[error("Missing value for {0}")]
+        MissingValue(String),
+        #[error("Invalid value for {0}: \"{1}\"")]
+        OverrideError(String, String),
+        #[error("The settings do not match their format: {0}")]
+        Conversion(#[from] serde_json::Error),
+    }
+
+    #[derive(Error, Debug)]
+    pub enum HistoryError {
+        #[error("There is no capture {0} in the history")]
+        MissingEntry(usize),
+    }
+
+    impl RustyCaptureError {
+        /// What the user can do about the error, shown below its message
+        pub fn hint(&self) -> Option<&'static str> {
+            match self {
+                RustyCaptureError::Capture(CaptureError::MissingScreen { .. }) => Some("Choose another screen in the toolbar or in the preset."),
+                RustyCaptureError::Capture(_) => Some("Check that the screen is still connected, or that the app is allowed to record the screen."),
+                RustyCaptureError::Edit(EditError::TooLarge { .. }) => Some("Choose a smaller size, or crop the image first."),
+                RustyCaptureError::NotADirectory(_) => Some("Choose an existing folder in the settings or in the save dialog."),
+                RustyCaptureError::Io { source, .. } if source.kind() == std::io::ErrorKind::PermissionDenied => Some("Choose a folder you are allowed to write to."),
+                RustyCaptureError::Encoding(EncodingError::UnsupportedFormat(_)) => Some("Save the screenshot as PNG, JPG or GIF."),
+                RustyCaptureError::Encoding(EncodingError::Decode { .. }) => Some("Open a PNG, JPG, GIF, BMP, WebP or another image the app can read."),
+                RustyCaptureError::Encoding(EncodingError::Json { .. }) => Some("Fix the file, or delete it so that it is written again."),
+                RustyCaptureError::Clipboard(ClipboardError::NotSaved(_)) => Some("Save the screenshot, or copy the image itself by changing the clipboard mode in the settings."),
+                RustyCaptureError::Clipboard(ClipboardError::NoImage) => Some("Copy an image first, or open an image file with Ctrl+O."),
+                RustyCaptureError::Hotkey(HotkeyError::Unavailable(_) | HotkeyError::Register { .. }) => Some("Choose another combination in the settings."),
+                RustyCaptureError::Settings(SettingsError::UnknownOption(_) | SettingsError::MissingValue(_) | SettingsError::OverrideError(..)) => Some("Run rusty_capture --print-config to list every setting and its value."),
+                RustyCaptureError::Settings(SettingsError::FormatError(_) | SettingsError::Conversion(_)) => Some("Fix the settings file, or delete it to start again from the default settings."),
+                _ => None,
+            }
+        }
+    }
+
+    /// Attaches the file an operation was working on to its error
+    pub trait WithPath<T> {
+        fn with_path(self, path: &Path) -> Result<T, RustyCaptureError>;
+    }
+
+    impl<T> WithPath<T> for std::io::Result<T> {
+        fn with_path(self, path: &Path) -> Result<T, RustyCaptureError> {
+            self.map_err(|source| RustyCaptureError::Io { path: path.to_path_buf(), source })
+        }
+    }
+
+    impl<T> WithPath<T> for serde_json::Result<T> {
+        fn with_path(self, path: &Path) -> Result<T, RustyCaptureError> {
+            self.map_err(|source| EncodingError::Json { path: path.to_path_buf(), source }.into())
+        }
+    }
+}
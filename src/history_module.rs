@@ -0,0 +1,189 @@
+#![allow(dead_code)]
+pub mod history_module {
+    use std::fmt::{Display, Formatter};
+    use std::fs;
+    use std::io::ErrorKind;
+    use std::path::{Path, PathBuf};
+    use std::thread::{self, JoinHandle};
+    use chrono::{DateTime, Local, TimeZone};
+    use image::{DynamicImage, ImageFormat};
+    use serde::{Serialize, Deserialize};
+    use crate::error_module::error_module::{EncodingError, HistoryError, RustyCaptureError, WithPath};
+    use crate::screenshots_module::screenshot_module::Screenshot;
+    use crate::settings_module::settings_module::data_dir;
+
+    const INDEX_FILE: &str = "history.json";
+
+    /// Where a capture of the history comes from
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum CaptureSource {
+        Screen(usize),
+        Clipboard,
+        SavedFile,
+    }
+
+    impl Display for CaptureSource {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            match self {
+                CaptureSource::Screen(screen) => write!(f, "Screen {}", screen),
+                CaptureSource::Clipboard => write!(f, "Clipboard"),
+                CaptureSource::SavedFile => write!(f, "Saved file"),
+            }
+        }
+    }
+
+    /// A capture listed in the history: the ones taken by the app are copied in the history
+    /// directory, the screenshots found in the save directory are listed where they are
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct HistoryEntry {
+        pub file: PathBuf,
+        /// Seconds since the Unix epoch
+        pub taken_at: i64,
+        pub width: u32,
+        pub height: u32,
+        pub source: CaptureSource,
+    }
+
+    impl HistoryEntry {
+        pub fn taken_at(&self) -> String {
+            Local.timestamp_opt(self.taken_at, 0).single()
+                .map(|time| time.format("%d-%m-%Y %H:%M:%S").to_string())
+                .unwrap_or_default()
+        }
+
+        /// The image shrunk to fit in a square of the given size
+        pub fn thumbnail(&self, size: u32) -> Result<DynamicImage, RustyCaptureError> {
+            let image = image::open(&self.file).map_err(|source| EncodingError::Decode { path: self.file.clone(), source })?;
+            Ok(image.thumbnail(size, size))
+        }
+
+        pub fn open(&self) -> Result<Screenshot, RustyCaptureError> {
+            Screenshot::from_path(&self.file)
+        }
+    }
+
+    /// The last captures taken, newest first, stored in a directory along with their index
+    pub struct History {
+        /// None when there is nowhere to keep the captures, the history is off then
+        dir: Option<PathBuf>,
+        pub entries: Vec<HistoryEntry>,
+        /// Threads still writing the file of a capture
+        writing: Vec<JoinHandle<()>>,
+    }
+
+    impl History {
+        pub fn new(dir: &Path) -> History {
+            History { dir: Some(dir.to_path_buf()), entries: Vec::new(), writing: Vec::new() }
+        }
+
+        /// A history which keeps nothing
+        pub fn disabled() -> History {
+            History { dir: None, entries: Vec::new(), writing: Vec::new() }
+        }
+
+        pub fn is_enabled(&self) -> bool {
+            self.dir.is_some()
+        }
+
+        /// Reads the index of the directory, the captures whose file is gone are forgotten
+        pub fn load(dir: &Path) -> Result<History, RustyCaptureError> {
+            let index_file = dir.join(INDEX_FILE);
+            let mut entries: Vec<HistoryEntry> = match fs::read_to_string(&index_file) {
+                Ok(index) => serde_json::from_str(&index).with_path(&index_file)?,
+                Err(e) if e.kind() == ErrorKind::NotFound => Vec::new(),
+                Err(e) => return Err(RustyCaptureError::Io { path: index_file, source: e }),
+            };
+            entries.retain(|entry| entry.file.is_file());
+            Ok(History { dir: Some(dir.to_path_buf()), entries, writing: Vec::new() })
+        }
+
+        /// Keeps a copy of the capture, then deletes the oldest ones past the limit; the PNG is
+        /// encoded by another thread, so a big capture does not freeze the window
+        pub fn add(&mut self, screenshot: &Screenshot, source: CaptureSource, limit: usize) -> Result<(), RustyCaptureError> {
+            let Some(dir) = &self.dir else {
+                return Ok(());
+            };
+            if limit == 0 {
+                return self.prune(limit);
+            }
+            fs::create_dir_all(dir).with_path(dir)?;
+            let now = Local::now();
+            let file = dir.join(format!("capture-{}.png", now.format("%Y%m%d-%H%M%S-%3f")));
+            let image = screenshot.get_image();
+            self.entries.insert(0, HistoryEntry {
+                file: file.clone(),
+                taken_at: now.timestamp(),
+                width: image.width(),
+                height: image.height(),
+                source,
+            });
+            self.writing.retain(|thread| !thread.is_finished());
+            self.writing.push(thread::spawn(move || {
+                // a capture whose file is missing is forgotten when the history is loaded
+                if let Err(source) = image.save_with_format(&file, ImageFormat::Png) {
+                    log::error!("{}", RustyCaptureError::from(EncodingError::Encode { path: file, format: ImageFormat::Png, source }));
+                }
+            }));
+            self.prune(limit)
+        }
+
+        /// Deletes the captures past the limit, oldest first
+        pub fn prune(&mut self, limit: usize) -> Result<(), RustyCaptureError> {
+            while self.entries.len() > limit {
+                self.remove(self.entries.len() - 1)?;
+            }
+            self.write_index()
+        }
+
+        /// Deletes a capture and its file
+        pub fn remove(&mut self, index: usize) -> Result<(), RustyCaptureError> {
+            if index >= self.entries.len() {
+                return Err(HistoryError::MissingEntry(index).into());
+            }
+            // the file of a capture could be written after it is deleted otherwise
+            for thread in self.writing.drain(..) {
+                let _ = thread.join();
+            }
+            let entry = self.entries.remove(index);
+            match fs::remove_file(&entry.file) {
+                Err(e) if e.kind() != ErrorKind::NotFound => return Err(RustyCaptureError::Io { path: entry.file, source: e }),
+                _ => {}
+            }
+            self.write_index()
+        }
+
+        fn write_index(&self) -> Result<(), RustyCaptureError> {
+            let Some(dir) = &self.dir else {
+                return Ok(());
+            };
+            let index_file = dir.join(INDEX_FILE);
+            fs::create_dir_all(dir).with_path(dir)?;
+            // written aside and renamed, so a crash never leaves half an index
+            let tmp = dir.join(format!("{}.tmp", INDEX_FILE));
+            fs::write(&tmp, serde_json::to_string_pretty(&self.entries).with_path(&index_file)?).with_path(&tmp)?;
+            fs::rename(&tmp, &index_file).with_path(&index_file)
+        }
+    }
+
+    /// Where the history keeps its captures, inside the data directory if there is one
+    pub fn history_dir() -> Option<PathBuf> {
+        data_dir().map(|dir| dir.join("history"))
+    }
+
+    /// Screenshots saved by the app in the given directory, newest first
+    pub fn saved_screenshots(dir: &Path) -> Vec<HistoryEntry> {
+        let files = fs::read_dir(dir).into_iter().flatten().flatten().map(|entry| entry.path());
+        let mut entries: Vec<HistoryEntry> = files
+            .filter(|file| file.file_name().is_some_and(|name| name.to_string_lossy().starts_with("screenshot-")))
+            .filter_map(|file| {
+                let (width, height) = image::image_dimensions(&file).ok()?;
+                let modified = fs::metadata(&file).and_then(|metadata| metadata.modified()).ok()?;
+                let taken_at = DateTime::<Local>::from(modified).timestamp();
+                Some(HistoryEntry { file, taken_at, width, height, source: CaptureSource::SavedFile })
+            })
+            .collect();
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.taken_at));
+        entries
+    }
+}
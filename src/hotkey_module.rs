@@ -1,256 +1,434 @@
 #![allow(dead_code)]
 pub mod hotkey_module {
-    use std::error::Error;
+    use std::collections::HashMap;
+    use std::fmt::{Display, Formatter};
+    use std::str::FromStr;
     use global_hotkey::GlobalHotKeyManager;
     use global_hotkey::hotkey::{Code, HotKey, Modifiers};
+    use serde::{Serialize, Deserialize};
+    use crate::error_module::error_module::{HotkeyError, RustyCaptureError};
 
-    pub enum KeyType {
-        Quick,
+
+    /// A full key combination, made of any set of modifiers and a single key, written in a
+    /// human-readable form like `Ctrl+Shift+F4`
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct Accelerator {
+        pub modifiers: Modifiers,
+        pub key: Code,
+    }
+
+    impl Accelerator {
+        pub fn new(modifiers: Modifiers, key: Code) -> Self {
+            Accelerator { modifiers, key }
+        }
+
+        pub fn to_hotkey(self) -> HotKey {
+            HotKey::new(Some(self.modifiers), self.key)
+        }
+    }
+
+    impl FromStr for Accelerator {
+        type Err = HotkeyError;
+
+        /// Parses modifiers and key separated by '+', modifiers first; names are case-insensitive
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let tokens: Vec<&str> = s.split('+').map(|t| t.trim()).collect();
+            let (key, modifiers) = tokens.split_last().ok_or_else(|| HotkeyError::ParseError(s.to_string()))?;
+            let mut accelerator = Accelerator::new(Modifiers::empty(), parse_key(key).map_err(|_| HotkeyError::ParseError(s.to_string()))?);
+            for modifier in modifiers {
+                let modifier = match modifier.to_uppercase().as_str() {
+                    "CTRL" | "CONTROL" => Modifiers::CONTROL,
+                    "ALT" | "OPTION" => Modifiers::ALT,
+                    "SHIFT" => Modifiers::SHIFT,
+                    "SUPER" | "WIN" | "META" | "CMD" | "COMMAND" => Modifiers::SUPER,
+                    _ => return Err(HotkeyError::ParseError(s.to_string())),
+                };
+                accelerator.modifiers.insert(modifier);
+            }
+            Ok(accelerator)
+        }
+    }
+
+    impl Display for Accelerator {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            for (modifier, name) in [(Modifiers::CONTROL, "Ctrl"), (Modifiers::ALT, "Alt"), (Modifiers::SHIFT, "Shift"), (Modifiers::SUPER, "Super")] {
+                if self.modifiers.contains(modifier) {
+                    write!(f, "{}+", name)?;
+                }
+            }
+            write!(f, "{}", key_name(self.key))
+        }
+    }
+
+    /// Parses a single key: letters and digits can be written alone ("A", "1"), every other key
+    /// with its name ("F4", "PrintScreen", "ArrowUp", ...) or a common abbreviation ("Esc", "Up")
+    pub fn parse_key(key: &str) -> Result<Code, HotkeyError> {
+        let invalid = |_| HotkeyError::KeyError(key.to_string());
+        let mut chars = key.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            if c.is_ascii_alphabetic() {
+                return Code::from_str(&format!("Key{}", c.to_ascii_uppercase())).map_err(invalid);
+            }
+            if c.is_ascii_digit() {
+                return Code::from_str(&format!("Digit{}", c)).map_err(invalid);
+            }
+        }
+        let upper = key.to_uppercase();
+        let code = match upper.as_str() {
+            "ESC" | "ESCAPE" => Code::Escape,
+            "UP" | "ARROWUP" => Code::ArrowUp,
+            "DOWN" | "ARROWDOWN" => Code::ArrowDown,
+            "LEFT" | "ARROWLEFT" => Code::ArrowLeft,
+            "RIGHT" | "ARROWRIGHT" => Code::ArrowRight,
+            "DEL" | "DELETE" => Code::Delete,
+            "INS" | "INSERT" => Code::Insert,
+            "PRTSC" | "PRINT" | "PRINTSCREEN" => Code::PrintScreen,
+            "PGUP" | "PAGEUP" => Code::PageUp,
+            "PGDN" | "PAGEDOWN" => Code::PageDown,
+            "HOME" => Code::Home,
+            "END" => Code::End,
+            "SPACE" => Code::Space,
+            "TAB" => Code::Tab,
+            "ENTER" | "RETURN" => Code::Enter,
+            "BACKSPACE" => Code::Backspace,
+            _ if upper.starts_with('F') && upper[1..].parse::<u8>().is_ok() => Code::from_str(&upper).map_err(invalid)?,
+            _ => Code::from_str(key).map_err(invalid)?,
+        };
+        Ok(code)
+    }
+
+    /// Inverse of parse_key: letters and digits are shown without the "Key"/"Digit" prefix
+    pub fn key_name(key: Code) -> String {
+        let name = key.to_string();
+        match name.strip_prefix("Key").or_else(|| name.strip_prefix("Digit")) {
+            Some(short) if short.len() == 1 => short.to_string(),
+            _ => name,
+        }
+    }
+
+    /// Every command of the app, each of them can be bound to a hotkey: capture triggers are
+    /// global hotkeys of the OS, everything else is a shortcut of the focused window
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum Action {
+        QuickScreenshot,
         NewScreenshot,
         Save,
+        CopyToClipboard,
+        Settings,
+        RotateLeft,
+        RotateRight,
+        Adjust,
+        Crop,
         Pen,
+        Highlight,
         Rubber,
+        Shapes,
+        Text,
+        StepMarker,
+        Magnify,
+        Spotlight,
+        Undo,
+        Redo,
+        ZoomIn,
+        ZoomOut,
+        ZoomReset,
+        SwapColors,
+        PasteNew,
+        PasteOverlay,
+        Open,
+        History,
+        CloseTab,
+        NextTab,
+        /// Runs the capture preset with the given index, its hotkey is stored in the preset
+        #[serde(skip)]
+        Preset(usize),
+    }
+
+    impl Action {
+        pub const ALL: [Action; 29] = [
+            Action::QuickScreenshot,
+            Action::NewScreenshot,
+            Action::Save,
+            Action::CopyToClipboard,
+            Action::Settings,
+            Action::RotateLeft,
+            Action::RotateRight,
+            Action::Adjust,
+            Action::Crop,
+            Action::Pen,
+            Action::Highlight,
+            Action::Rubber,
+            Action::Shapes,
+            Action::Text,
+            Action::StepMarker,
+            Action::Magnify,
+            Action::Spotlight,
+            Action::Undo,
+            Action::Redo,
+            Action::ZoomIn,
+            Action::ZoomOut,
+            Action::ZoomReset,
+            Action::SwapColors,
+            Action::PasteNew,
+            Action::PasteOverlay,
+            Action::Open,
+            Action::History,
+            Action::CloseTab,
+            Action::NextTab,
+        ];
+
+        /// Only capture triggers have to work while another application is focused
+        pub fn is_global(&self) -> bool {
+            matches!(self, Action::QuickScreenshot | Action::NewScreenshot | Action::Preset(_))
+        }
+
+        /// Tells whether the hotkey of this action has to be listening in the given context:
+        /// only the capture commands make sense before a screenshot is taken, nothing while paused
+        pub fn is_active_in(&self, context: ShortcutContext) -> bool {
+            match context {
+                ShortcutContext::Pause => false,
+                ShortcutContext::ScreenshotWaiting => matches!(self, Action::QuickScreenshot | Action::NewScreenshot | Action::Settings | Action::PasteNew | Action::Open | Action::History | Action::Preset(_)),
+                ShortcutContext::ScreenshotDone => true,
+            }
+        }
+    }
+
+    impl Display for Action {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Action::QuickScreenshot => write!(f, "Quick Screenshot"),
+                Action::NewScreenshot => write!(f, "New Screenshot"),
+                Action::Save => write!(f, "Save Screenshot"),
+                Action::CopyToClipboard => write!(f, "Copy to clipboard"),
+                Action::Settings => write!(f, "Settings"),
+                Action::RotateLeft => write!(f, "Rotate left"),
+                Action::RotateRight => write!(f, "Rotate right"),
+                Action::Adjust => write!(f, "Adjust image"),
+                Action::Crop => write!(f, "Crop tool"),
+                Action::Pen => write!(f, "Pen tool"),
+                Action::Highlight => write!(f, "Highlight tool"),
+                Action::Rubber => write!(f, "Rubber tool"),
+                Action::Shapes => write!(f, "Shapes tool"),
+                Action::Text => write!(f, "Text tool"),
+                Action::StepMarker => write!(f, "Step marker tool"),
+                Action::Magnify => write!(f, "Magnify tool"),
+                Action::Spotlight => write!(f, "Spotlight tool"),
+                Action::Undo => write!(f, "Undo"),
+                Action::Redo => write!(f, "Redo"),
+                Action::ZoomIn => write!(f, "Zoom in"),
+                Action::ZoomOut => write!(f, "Zoom out"),
+                Action::ZoomReset => write!(f, "Reset zoom"),
+                Action::SwapColors => write!(f, "Swap colors"),
+                Action::PasteNew => write!(f, "Paste as new capture"),
+                Action::PasteOverlay => write!(f, "Paste as overlay"),
+                Action::Open => write!(f, "Open image"),
+                Action::History => write!(f, "Capture history"),
+                Action::CloseTab => write!(f, "Close tab"),
+                Action::NextTab => write!(f, "Next tab"),
+                Action::Preset(index) => write!(f, "Capture preset {}", index + 1),
+            }
+        }
     }
 
-    pub enum ActiveShortcuts {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum ShortcutContext {
         ScreenshotDone,
         ScreenshotWaiting,
         Pause,
     }
 
+    /// A hotkey bound to an action; global ones are registered to the OS only while enabled,
+    /// window shortcuts are never registered and just looked up by the window
+    struct Binding {
+        accelerator: Accelerator,
+        hotkey: HotKey,
+        enabled: bool,
+    }
+
     pub struct HotkeyManager {
-        manager: GlobalHotKeyManager,
-        quick_screenshot: (Option<HotKey>, bool),
-        new_screenshot: (Option<HotKey>, bool),
-        save: (Option<HotKey>, bool),
-        pen: (Option<HotKey>, bool),
-        rubber: (Option<HotKey>, bool),
+        /// None when the system does not support global hotkeys, like some Wayland sessions:
+        /// the window shortcuts keep working
+        manager: Option<GlobalHotKeyManager>,
+        bindings: HashMap<Action, Binding>,
+        context: ShortcutContext,
     }
 
     impl HotkeyManager {
-        pub fn new() -> Result<Self, Box<dyn Error>> {
+        pub fn new() -> Result<Self, RustyCaptureError> {
             Ok(HotkeyManager {
-                manager: GlobalHotKeyManager::new()?,
-                quick_screenshot: (None, true),
-                new_screenshot: (None, true),
-                save: (None, true),
-                pen: (None, true),
-                rubber: (None, true),
+                manager: Some(GlobalHotKeyManager::new().map_err(HotkeyError::Manager)?),
+                bindings: HashMap::new(),
+                context: ShortcutContext::ScreenshotWaiting,
             })
         }
-        pub fn register_new_hotkey(&mut self, modifier: Option<Modifiers>, key: Code, key_type: KeyType) -> Result<u32, Box<dyn Error>> {
-            match key_type {
-                KeyType::Quick => {
-                    let bool_now = self.quick_screenshot.1;
-                    if self.quick_screenshot.0.is_some() && self.quick_screenshot.1 == true {
-                        self.manager.unregister(self.quick_screenshot.0.unwrap())?;
-                    }
-                    let hk = HotKey::new(modifier, key);
-                    if bool_now {
-                        self.manager.register(hk)?;
-                        self.quick_screenshot = (Some(hk), true);
-                    } else {
-                        self.quick_screenshot = (Some(hk), false);
-                    }
-                    Ok(hk.id())
-                }
-                KeyType::NewScreenshot => {
-                    let bool_now = self.new_screenshot.1;
-                    if self.new_screenshot.0.is_some() && self.new_screenshot.1 == true {
-                        self.manager.unregister(self.new_screenshot.0.unwrap())?;
-                    }
-                    let hk = HotKey::new(modifier, key);
-                    if bool_now {
-                        self.manager.register(hk)?;
-                        self.new_screenshot = (Some(hk), true);
-                    } else {
-                        self.new_screenshot = (Some(hk), false);
-                    }
-                    Ok(hk.id())
-                }
-                KeyType::Save => {
-                    let bool_now = self.save.1;
-                    if self.save.0.is_some() && self.save.1 == true {
-                        self.manager.unregister(self.save.0.unwrap())?;
-                    }
-                    let hk = HotKey::new(modifier, key);
-                    if bool_now {
-                        self.manager.register(hk)?;
-                        self.save = (Some(hk), true);
-                    } else {
-                        self.save = (Some(hk), false);
-                    }
-                    Ok(hk.id())
-                }
-                KeyType::Pen => {
-                    let bool_now = self.pen.1;
-                    if self.pen.0.is_some() && self.pen.1 == true {
-                        self.manager.unregister(self.pen.0.unwrap())?;
-                    }
-                    let hk = HotKey::new(modifier, key);
-                    if bool_now {
-                        self.manager.register(hk)?;
-                        self.pen = (Some(hk), true);
-                    } else {
-                        self.pen = (Some(hk), false);
-                    }
-                    Ok(hk.id())
-                }
-                KeyType::Rubber => {
-                    let bool_now = self.rubber.1;
-                    if self.rubber.0.is_some() && self.rubber.1 == true {
-                        self.manager.unregister(self.rubber.0.unwrap())?;
-                    }
-                    let hk = HotKey::new(modifier, key);
-                    if bool_now {
-                        self.manager.register(hk)?;
-                        self.rubber = (Some(hk), true);
-                    } else {
-                        self.rubber = (Some(hk), false);
-                    }
-                    Ok(hk.id())
-                }
+
+        /// A manager handling only the window shortcuts, for when new fails
+        pub fn without_global_hotkeys() -> Self {
+            HotkeyManager {
+                manager: None,
+                bindings: HashMap::new(),
+                context: ShortcutContext::ScreenshotWaiting,
             }
         }
 
-        pub fn disable_shortcut(&mut self, key_type: KeyType) -> Result<(), Box<dyn Error>> {
-            return match key_type {
-                KeyType::Quick => {
-                    if self.quick_screenshot.0.is_some() && self.quick_screenshot.1 == true {
-                        self.manager.unregister(self.quick_screenshot.0.unwrap())?;
-                        self.quick_screenshot.1 = false;
-                    }
-                    Ok(())
-                }
-                KeyType::Pen => {
-                    if self.pen.0.is_some() && self.pen.1 == true {
-                        self.manager.unregister(self.pen.0.unwrap())?;
-                        self.pen.1 = false;
-                    }
-                    Ok(())
-                }
-                KeyType::NewScreenshot => {
-                    if self.new_screenshot.0.is_some() && self.new_screenshot.1 == true {
-                        self.manager.unregister(self.new_screenshot.0.unwrap())?;
-                        self.new_screenshot.1 = false;
-                    }
-                    Ok(())
-                }
-                KeyType::Rubber => {
-                    if self.rubber.0.is_some() && self.rubber.1 == true {
-                        self.manager.unregister(self.rubber.0.unwrap())?;
-                        self.rubber.1 = false;
-                    }
-                    Ok(())
-                }
-                KeyType::Save => {
-                    if self.save.0.is_some() && self.save.1 == true {
-                        self.manager.unregister(self.save.0.unwrap())?;
-                        self.save.1 = false;
-                    }
-                    Ok(())
-                }
-            };
+        pub fn has_global_hotkeys(&self) -> bool {
+            self.manager.is_some()
         }
-        pub fn enable_shortcut(&mut self, key_type: KeyType) -> Result<(), Box<dyn Error>> {
-            return match key_type {
-                KeyType::Quick => {
-                    if self.quick_screenshot.0.is_some() && self.quick_screenshot.1 == false {
-                        self.manager.register(self.quick_screenshot.0.unwrap())?;
-                        self.quick_screenshot.1 = true;
-                    }
-                    Ok(())
-                }
-                KeyType::Pen => {
-                    if self.pen.0.is_some() && self.pen.1 == false {
-                        self.manager.register(self.pen.0.unwrap())?;
-                        self.pen.1 = true;
-                    }
-                    Ok(())
-                }
-                KeyType::NewScreenshot => {
-                    if self.new_screenshot.0.is_some() && self.new_screenshot.1 == false {
-                        self.manager.register(self.new_screenshot.0.unwrap())?;
-                        self.new_screenshot.1 = true;
-                    }
-                    Ok(())
-                }
-                KeyType::Rubber => {
-                    if self.rubber.0.is_some() && self.rubber.1 == false {
-                        self.manager.register(self.rubber.0.unwrap())?;
-                        self.rubber.1 = true;
-                    }
-                    Ok(())
-                }
-                KeyType::Save => {
-                    if self.save.0.is_some() && self.save.1 == false {
-                        self.manager.register(self.save.0.unwrap())?;
-                        self.save.1 = true;
-                    }
-                    Ok(())
-                }
-            };
+
+        /// Binds the action to the accelerator replacing its previous hotkey, which is enabled
+        /// straight away if the action is active in the current context
+        pub fn register(&mut self, action: Action, accelerator: Accelerator) -> Result<u32, RustyCaptureError> {
+            self.unregister(action)?;
+            let hotkey = accelerator.to_hotkey();
+            self.bindings.insert(action, Binding { accelerator, hotkey, enabled: false });
+            if action.is_active_in(self.context) {
+                self.enable(action)?;
+            }
+            Ok(hotkey.id())
         }
-        pub fn set_active_shortcuts(&mut self, active_shortcuts: ActiveShortcuts) -> Result<(), Box<dyn Error>> {
-            match active_shortcuts {
-                ActiveShortcuts::Pause => {
-                    self.disable_shortcut(KeyType::Save)?;
-                    self.disable_shortcut(KeyType::Pen)?;
-                    self.disable_shortcut(KeyType::Rubber)?;
-                    self.disable_shortcut(KeyType::NewScreenshot)?;
-                    self.disable_shortcut(KeyType::Quick)?;
-                }
-                ActiveShortcuts::ScreenshotWaiting => {
-                    self.disable_shortcut(KeyType::Rubber)?;
-                    self.disable_shortcut(KeyType::Pen)?;
-                    self.enable_shortcut(KeyType::Quick)?;
-                    self.enable_shortcut(KeyType::NewScreenshot)?;
-                    self.disable_shortcut(KeyType::Save)?;
+
+        pub fn unregister(&mut self, action: Action) -> Result<(), RustyCaptureError> {
+            self.disable(action)?;
+            self.bindings.remove(&action);
+            Ok(())
+        }
+
+        /// Replaces every binding with the given ones; a hotkey which cannot be registered does
+        /// not stop the others, the first failure is returned
+        pub fn register_all(&mut self, bindings: Vec<(Action, Accelerator)>) -> Result<(), RustyCaptureError> {
+            let actions: Vec<Action> = self.bindings.keys().copied().collect();
+            for action in actions {
+                self.unregister(action)?;
+            }
+            let mut result = Ok(());
+            for (action, accelerator) in bindings {
+                let registered = self.register(action, accelerator);
+                if let Err(e) = &registered {
+                    log::warn!("{} cannot be bound to {}: {}", action, accelerator, e);
                 }
-                ActiveShortcuts::ScreenshotDone => {
-                    self.enable_shortcut(KeyType::Rubber)?;
-                    self.enable_shortcut(KeyType::Pen)?;
-                    self.enable_shortcut(KeyType::Quick)?;
-                    self.enable_shortcut(KeyType::NewScreenshot)?;
-                    self.enable_shortcut(KeyType::Save)?;
+                if result.is_ok() {
+                    result = registered.map(|_| ());
                 }
             }
-            return Ok(());
+            result
         }
 
-        pub fn get_key(&self, key_type: KeyType) -> Option<u32> {
-            return match key_type {
-                KeyType::Quick => {
-                    if self.quick_screenshot.0.is_some() {
-                        return Some(self.quick_screenshot.0.unwrap().id());
-                    }
-                    None
-                }
-                KeyType::Save => {
-                    if self.save.0.is_some() {
-                        return Some(self.save.0.unwrap().id());
-                    }
-                    None
-                }
-                KeyType::Rubber => {
-                    if self.rubber.0.is_some() {
-                        return Some(self.rubber.0.unwrap().id());
-                    }
-                    None
+        pub fn enable(&mut self, action: Action) -> Result<(), RustyCaptureError> {
+            if !action.is_global() {
+                return Ok(());
+            }
+            let Some(manager) = &self.manager else {
+                return Ok(());
+            };
+            if let Some(binding) = self.bindings.get_mut(&action) {
+                if !binding.enabled {
+                    manager.register(binding.hotkey)
+                        .map_err(|source| HotkeyError::Register { hotkey: binding.accelerator.to_string(), source })?;
+                    binding.enabled = true;
+                    log::debug!("Global hotkey {} enabled for {}", binding.accelerator, action);
                 }
-                KeyType::Pen => {
-                    if self.pen.0.is_some() {
-                        return Some(self.pen.0.unwrap().id());
-                    }
-                    None
+            }
+            Ok(())
+        }
+
+        pub fn disable(&mut self, action: Action) -> Result<(), RustyCaptureError> {
+            let Some(manager) = &self.manager else {
+                return Ok(());
+            };
+            if let Some(binding) = self.bindings.get_mut(&action) {
+                if binding.enabled {
+                    manager.unregister(binding.hotkey).map_err(HotkeyError::Manager)?;
+                    binding.enabled = false;
+                    log::debug!("Global hotkey {} disabled for {}", binding.accelerator, action);
                 }
-                KeyType::NewScreenshot => {
-                    if self.new_screenshot.0.is_some() {
-                        return Some(self.new_screenshot.0.unwrap().id());
-                    }
-                    None
+            }
+            Ok(())
+        }
+
+        /// Enables the hotkeys of the actions active in the given context and disables the others,
+        /// returning the first one which failed
+        pub fn set_context(&mut self, context: ShortcutContext) -> Result<(), RustyCaptureError> {
+            log::debug!("Shortcut context {:?}", context);
+            self.context = context;
+            let actions: Vec<Action> = self.bindings.keys().copied().collect();
+            let mut result = Ok(());
+            for action in actions {
+                let changed = if action.is_active_in(context) { self.enable(action) } else { self.disable(action) };
+                if result.is_ok() {
+                    result = changed;
                 }
+            }
+            result
+        }
+
+        /// Returns the action bound to the hotkey which generated the event with the given id
+        pub fn get_action(&self, event_id: u32) -> Option<Action> {
+            self.bindings.iter()
+                .find(|(_, binding)| binding.hotkey.id() == event_id)
+                .map(|(action, _)| *action)
+        }
+
+        pub fn get_context(&self) -> ShortcutContext {
+            self.context
+        }
+
+        /// Checks whether the accelerator can be registered, trying it out unless it is already
+        /// listening for one of our actions
+        pub fn check_available(&mut self, accelerator: Accelerator) -> Result<(), RustyCaptureError> {
+            if self.bindings.values().any(|binding| binding.enabled && binding.accelerator == accelerator) {
+                return Ok(());
+            }
+            let Some(manager) = &self.manager else {
+                return Ok(());
             };
+            let hotkey = accelerator.to_hotkey();
+            manager.register(hotkey).map_err(|_| HotkeyError::Unavailable(accelerator.to_string()))?;
+            manager.unregister(hotkey).map_err(HotkeyError::Manager)?;
+            Ok(())
+        }
+
+        /// Returns the window shortcuts active in the current context
+        pub fn get_local_bindings(&self) -> Vec<(Action, Accelerator)> {
+            self.bindings.iter()
+                .filter(|(action, _)| !action.is_global() && action.is_active_in(self.context))
+                .map(|(action, binding)| (*action, binding.accelerator))
+                .collect()
+        }
+
+        pub fn get_accelerator(&self, action: Action) -> Option<Accelerator> {
+            self.bindings.get(&action).map(|binding| binding.accelerator)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::str::FromStr;
+        use global_hotkey::hotkey::{Code, Modifiers};
+        use super::Accelerator;
+
+        #[test]
+        fn accelerators_round_trip() {
+            for text in ["Ctrl+Shift+F4", "Alt+A", "Super+1", "PrintScreen", "Ctrl+Alt+Shift+Super+Escape", "Shift+ArrowUp", "Ctrl+Space"] {
+                let accelerator = Accelerator::from_str(text).unwrap();
+                assert_eq!(accelerator.to_string(), text);
+                assert_eq!(Accelerator::from_str(&accelerator.to_string()).unwrap(), accelerator);
+            }
+        }
+
+        #[test]
+        fn names_are_case_insensitive_with_aliases() {
+            let accelerator = Accelerator::from_str("shift + control + esc").unwrap();
+            assert_eq!(accelerator, Accelerator::new(Modifiers::CONTROL | Modifiers::SHIFT, Code::Escape));
+            assert_eq!(accelerator.to_string(), "Ctrl+Shift+Escape");
+            assert_eq!(Accelerator::from_str("cmd+q").unwrap(), Accelerator::new(Modifiers::SUPER, Code::KeyQ));
+        }
+
+        #[test]
+        fn invalid_accelerators_are_refused() {
+            for text in ["", "Ctrl+", "Hyper+A", "Ctrl+NotAKey", "F99"] {
+                assert!(Accelerator::from_str(text).is_err(), "{} was accepted", text);
+            }
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,161 @@
+#![allow(dead_code)]
+pub mod log_module {
+    use std::fmt::{Display, Formatter};
+    use std::fs::{self, File, OpenOptions};
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
+    use std::sync::Mutex;
+    use chrono::Local;
+    use log::{Level, LevelFilter, Log, Metadata, Record};
+    use serde::{Serialize, Deserialize};
+    use crate::error_module::error_module::{RustyCaptureError, WithPath};
+    use crate::settings_module::settings_module::state_dir;
+
+    const LOG_FILE: &str = "rusty-capture.log";
+    /// Size past which the log is rotated
+    const MAX_LOG_SIZE: u64 = 1024 * 1024;
+    /// Rotated logs kept besides the current one, as rusty-capture.log.1 (newest) and so on
+    const KEPT_LOGS: usize = 3;
+
+    /// The least severe messages written to the log
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum LogLevel {
+        Off,
+        Error,
+        Warn,
+        #[default]
+        Info,
+        Debug,
+        Trace,
+    }
+
+    impl LogLevel {
+        pub const ALL: [LogLevel; 6] = [LogLevel::Off, LogLevel::Error, LogLevel::Warn, LogLevel::Info, LogLevel::Debug, LogLevel::Trace];
+
+        pub fn filter(&self) -> LevelFilter {
+            match self {
+                LogLevel::Off => LevelFilter::Off,
+                LogLevel::Error => LevelFilter::Error,
+                LogLevel::Warn => LevelFilter::Warn,
+                LogLevel::Info => LevelFilter::Info,
+                LogLevel::Debug => LevelFilter::Debug,
+                LogLevel::Trace => LevelFilter::Trace,
+            }
+        }
+    }
+
+    impl Display for LogLevel {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            match self {
+                LogLevel::Off => write!(f, "Off"),
+                LogLevel::Error => write!(f, "Errors"),
+                LogLevel::Warn => write!(f, "Warnings"),
+                LogLevel::Info => write!(f, "Information"),
+                LogLevel::Debug => write!(f, "Debug"),
+                LogLevel::Trace => write!(f, "Trace"),
+            }
+        }
+    }
+
+    /// Writes every record on a line of the log file, rotating it when it gets too big; the
+    /// standard error is only used when there is no file to write to
+    struct FileLogger {
+        path: Option<PathBuf>,
+        file: Mutex<Option<File>>,
+    }
+
+    impl FileLogger {
+        fn rotate(&self, file: &mut Option<File>) {
+            let Some(path) = &self.path else {
+                return;
+            };
+            let size = file.as_ref().and_then(|file| file.metadata().ok()).map_or(0, |metadata| metadata.len());
+            if size < MAX_LOG_SIZE {
+                return;
+            }
+            *file = None;
+            let rotated = |index: usize| PathBuf::from(format!("{}.{}", path.display(), index));
+            for index in (1..KEPT_LOGS).rev() {
+                let _ = fs::rename(rotated(index), rotated(index + 1));
+            }
+            let _ = fs::rename(path, rotated(1));
+            *file = open_log(path).ok();
+        }
+    }
+
+    impl Log for FileLogger {
+        /// Libraries only report their warnings and errors, unless everything is traced
+        fn enabled(&self, metadata: &Metadata) -> bool {
+            let ours = metadata.target().starts_with(env!("CARGO_CRATE_NAME"));
+            metadata.level() <= log::max_level() && (ours || metadata.level() <= Level::Warn || log::max_level() == LevelFilter::Trace)
+        }
+
+        fn log(&self, record: &Record) {
+            if !self.enabled(record.metadata()) {
+                return;
+            }
+            let line = format!("{} {:<5} [{}] {}", Local::now().format("%Y-%m-%d %H:%M:%S%.3f"), record.level(), record.target(), record.args());
+            let written = self.file.lock().is_ok_and(|mut file| {
+                self.rotate(&mut file);
+                file.as_mut().is_some_and(|file| writeln!(file, "{}", line).is_ok())
+            });
+            if !written {
+                eprintln!("{}", line);
+            }
+        }
+
+        fn flush(&self) {
+            if let Ok(mut file) = self.file.lock() {
+                if let Some(file) = file.as_mut() {
+                    let _ = file.flush();
+                }
+            }
+        }
+    }
+
+    fn open_log(path: &Path) -> Result<File, RustyCaptureError> {
+        OpenOptions::new().create(true).append(true).open(path).with_path(path)
+    }
+
+    /// The file the log is written to, inside the state directory if there is one
+    pub fn log_path() -> Option<PathBuf> {
+        state_dir().map(|dir| dir.join(LOG_FILE))
+    }
+
+    /// Installs the logger, once at startup; when the log file cannot be opened the messages
+    /// still reach the standard error and the reason is returned
+    pub fn init_logging(level: LogLevel) -> Result<(), RustyCaptureError> {
+        let path = log_path();
+        let opened = match &path {
+            Some(path) => path.parent()
+                .map_or(Ok(()), |dir| fs::create_dir_all(dir).with_path(dir))
+                .and_then(|_| open_log(path))
+                .map(Some),
+            None => Ok(None),
+        };
+        let (file, result) = match opened {
+            Ok(file) => (file, Ok(())),
+            Err(e) => (None, Err(e)),
+        };
+        let no_file = path.is_none();
+        if log::set_boxed_logger(Box::new(FileLogger { path, file: Mutex::new(file) })).is_ok() {
+            set_log_level(level);
+        }
+        if no_file {
+            log::warn!("No folder was found for the log file, the log only goes to the standard error");
+        }
+        result
+    }
+
+    pub fn set_log_level(level: LogLevel) {
+        log::set_max_level(level.filter());
+    }
+
+    /// The last lines of the log file, for bug reports
+    pub fn recent_log(lines: usize) -> String {
+        let log = log_path().and_then(|path| fs::read_to_string(path).ok()).unwrap_or_default();
+        let all: Vec<&str> = log.lines().collect();
+        all[all.len().saturating_sub(lines)..].join("\n")
+    }
+}
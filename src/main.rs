@@ -1,14 +1,23 @@
+mod error_module;
 mod screenshots_module;
 mod hotkey_module;
 mod settings_module;
+mod history_module;
+mod log_module;
+mod metadata_module;
+mod recovery_module;
 mod state_module;
 
 use eframe::{NativeOptions, egui, IconData};
+use crate::error_module::error_module::{RustyCaptureError, SettingsError};
+use crate::log_module::log_module::{init_logging, set_log_level, LogLevel};
+use std::path::PathBuf;
+use crate::settings_module::settings_module::{print_config, resolve_settings, settings_path, Settings, Sources};
 use crate::state_module::state_module::ScreenshotStr;
 
-fn build_gui() -> () {
-    let icon = image::open("./resources/icon.png").expect("Failed to open icon path").to_rgba8();
-    let (icon_width, icon_height) = icon.dimensions();
+fn build_gui(settings_path: PathBuf, resolved: Result<(Settings, Sources), RustyCaptureError>) {
+    // embedded, so the app does not depend on the working directory
+    let icon = image::load_from_memory(include_bytes!("../resources/icon.png")).ok().map(|icon| icon.to_rgba8());
 
     //FONT CONF
     // Start with the default fonts (we will be adding to them rather than replacing them).
@@ -28,33 +37,66 @@ fn build_gui() -> () {
 
 
     //APP CONF
+    // the window opens where it was closed last time
+    let editor = resolved.as_ref().map(|(settings, _)| settings.editor.clone()).unwrap_or_default();
+    let window_size = editor.window_size.map_or(egui::vec2(650.0, 410.0), |[w, h]| egui::vec2(w.max(650.0), h.max(410.0)));
     let options = NativeOptions {
-        initial_window_size: Some(egui::vec2(650.0, 410.0)),
+        initial_window_size: Some(window_size),
+        initial_window_pos: editor.window_pos.map(|[x, y]| egui::pos2(x, y)),
         min_window_size: Some(egui::vec2(650.0, 410.0)),
-        icon_data: Some(IconData{
+        icon_data: icon.map(|icon| IconData {
+            width: icon.width(),
+            height: icon.height(),
             rgba: icon.into_raw(),
-            width: icon_width,
-            height: icon_height,
         }),
 
         ..Default::default()
     };
 
 
-    println!("Starting app");
+    log::info!("Starting Rusty Capture {}", env!("CARGO_PKG_VERSION"));
     eframe::run_native(
         "Rusty Capture",
         options,
         Box::new(|_cc| {
             _cc.egui_ctx.set_fonts(fonts);
-            Box::<ScreenshotStr>::new(ScreenshotStr::default())
+            Box::<ScreenshotStr>::new(ScreenshotStr::new(settings_path, resolved))
         }),
-    ).unwrap();
-    println!("closing eframe");
+    ).unwrap_or_else(|e| {
+        eprintln!("Could not open the window: {}", e);
+        std::process::exit(1);
+    });
+    log::info!("Closing Rusty Capture");
 }
 
 
 fn main() {
+    if std::env::args().any(|arg| arg == "--print-config") {
+        match print_config(&settings_path()) {
+            Ok(config) => print!("{}", config),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    // the level of the settings is only known once they are read, which already logs
+    if let Err(e) = init_logging(LogLevel::default()) {
+        eprintln!("The log is not written to a file: {}", e);
+    }
+    // wrong flags or environment variables are reported before opening the window, the app
+    // starts with the default settings when the file itself cannot be read
+    let settings_path = settings_path();
+    let resolved = resolve_settings(&settings_path);
+    match &resolved {
+        Ok((settings, _)) => set_log_level(settings.log_level),
+        Err(RustyCaptureError::Settings(e @ (SettingsError::UnknownOption(_) | SettingsError::MissingValue(_) | SettingsError::OverrideError(..)))) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        Err(_) => {}
+    }
     //HOTKEYS
-    build_gui();
+    build_gui(settings_path, resolved);
 }
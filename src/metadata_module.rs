@@ -0,0 +1,349 @@
+#![allow(dead_code)]
+pub mod metadata_module {
+    use std::io::Cursor;
+    use chrono::{DateTime, Local};
+    use image::{DynamicImage, ImageFormat, ImageOutputFormat};
+    use serde::{Deserialize, Serialize};
+    use crate::error_module::error_module::EncodingError;
+
+    const SOFTWARE: &str = concat!("Rusty Capture ", env!("CARGO_PKG_VERSION"));
+    const EXIF_HEADER: &[u8] = b"Exif\0\0";
+    const XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+
+    /// Title and description given to a capture by the user
+    #[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct Caption {
+        pub title: String,
+        pub description: String,
+    }
+
+    /// What is written in the saved PNG and JPEG files, unless the settings strip it
+    #[derive(Clone, Debug, Default)]
+    pub struct Metadata {
+        pub caption: Caption,
+        pub captured_at: Option<DateTime<Local>>,
+        /// Index of the captured screen, None for opened and pasted images
+        pub screen: Option<usize>,
+        pub capture_size: Option<(u32, u32)>,
+        /// Crops, rotations and the other changes of geometry, in the order they were applied
+        pub edits: Vec<String>,
+    }
+
+    impl Metadata {
+        /// Every field as a keyword and a text, the ones without a value are left out
+        pub fn fields(&self) -> Vec<(&'static str, String)> {
+            let mut fields = Vec::new();
+            if !self.caption.title.is_empty() {
+                fields.push(("Title", self.caption.title.clone()));
+            }
+            if !self.caption.description.is_empty() {
+                fields.push(("Description", self.caption.description.clone()));
+            }
+            if let Some(captured_at) = self.captured_at {
+                fields.push(("Creation Time", captured_at.to_rfc2822()));
+            }
+            fields.push(("Software", SOFTWARE.to_string()));
+            if let Some(screen) = self.screen {
+                fields.push(("Source", format!("Screen {}", screen)));
+            }
+            if let Some((width, height)) = self.capture_size {
+                fields.push(("Capture Size", format!("{}x{}", width, height)));
+            }
+            if !self.edits.is_empty() {
+                fields.push(("Edits", self.edits.join("; ")));
+            }
+            fields
+        }
+
+        /// The metadata as an XMP packet, read by most image viewers and catalogs
+        fn xmp(&self) -> String {
+            let mut properties = String::new();
+            if !self.caption.title.is_empty() {
+                properties.push_str(&format!("<dc:title><rdf:Alt><rdf:li xml:lang=\"x-default\">{}</rdf:li></rdf:Alt></dc:title>", escape(&self.caption.title)));
+            }
+            if !self.caption.description.is_empty() {
+                properties.push_str(&format!("<dc:description><rdf:Alt><rdf:li xml:lang=\"x-default\">{}</rdf:li></rdf:Alt></dc:description>", escape(&self.caption.description)));
+            }
+            if let Some(captured_at) = self.captured_at {
+                properties.push_str(&format!("<xmp:CreateDate>{}</xmp:CreateDate>", captured_at.to_rfc3339()));
+            }
+            properties.push_str(&format!("<xmp:CreatorTool>{}</xmp:CreatorTool>", SOFTWARE));
+            if let Some(screen) = self.screen {
+                properties.push_str(&format!("<rc:Screen>{}</rc:Screen>", screen));
+            }
+            if let Some((width, height)) = self.capture_size {
+                properties.push_str(&format!("<rc:CaptureSize>{}x{}</rc:CaptureSize>", width, height));
+            }
+            if !self.edits.is_empty() {
+                let edits: String = self.edits.iter().map(|edit| format!("<rdf:li>{}</rdf:li>", escape(edit))).collect();
+                properties.push_str(&format!("<rc:Edits><rdf:Seq>{}</rdf:Seq></rc:Edits>", edits));
+            }
+            format!(
+                "<?xpacket begin=\"\u{FEFF}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\
+                <x:xmpmeta xmlns:x=\"adobe:ns:meta/\"><rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\
+                <rdf:Description rdf:about=\"\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\" xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\" \
+                xmlns:rc=\"https://github.com/GiuDF1102/Rusty-Capture/ns/1.0/\">{}</rdf:Description></rdf:RDF></x:xmpmeta>\
+                <?xpacket end=\"w\"?>",
+                properties
+            )
+        }
+
+        /// A big-endian TIFF structure with the fields EXIF readers show: description, software
+        /// and date as ASCII, title and description again as the UTF-16 fields of Windows, and
+        /// an Exif IFD with the date of the capture and its details as the user comment
+        fn exif(&self) -> Vec<u8> {
+            let mut entries: Vec<(u16, u16, Vec<u8>)> = Vec::new();
+            let ascii = |text: &str| [text.as_bytes(), &[0]].concat();
+            let utf16 = |text: &str| text.encode_utf16().chain([0]).flat_map(u16::to_le_bytes).collect::<Vec<u8>>();
+            if !self.caption.description.is_empty() && self.caption.description.is_ascii() {
+                entries.push((0x010E, 2, ascii(&self.caption.description)));
+            }
+            entries.push((0x0131, 2, ascii(SOFTWARE)));
+            if let Some(captured_at) = self.captured_at {
+                entries.push((0x0132, 2, ascii(&captured_at.format("%Y:%m:%d %H:%M:%S").to_string())));
+            }
+            let exif_pointer = entries.len();
+            entries.push((0x8769, 4, vec![0; 4]));
+            if !self.caption.title.is_empty() {
+                entries.push((0x9C9B, 1, utf16(&self.caption.title)));
+            }
+            if !self.caption.description.is_empty() {
+                entries.push((0x9C9C, 1, utf16(&self.caption.description)));
+            }
+
+            let mut exif_entries: Vec<(u16, u16, Vec<u8>)> = vec![(0x9000, 7, b"0232".to_vec())];
+            if let Some(captured_at) = self.captured_at {
+                exif_entries.push((0x9003, 2, ascii(&captured_at.format("%Y:%m:%d %H:%M:%S").to_string())));
+            }
+            let details = self.fields().into_iter()
+                .filter(|(keyword, _)| !matches!(*keyword, "Title" | "Description"))
+                .map(|(keyword, text)| format!("{}: {}", keyword, text))
+                .collect::<Vec<_>>()
+                .join("\n");
+            // the character code comes first, UCS-2 is in the byte order of the file
+            let comment = if details.is_ascii() {
+                [b"ASCII\0\0\0", details.as_bytes()].concat()
+            } else {
+                [b"UNICODE\0".to_vec(), details.encode_utf16().flat_map(u16::to_be_bytes).collect()].concat()
+            };
+            exif_entries.push((0x9286, 7, comment));
+
+            // header, then IFD0 and the Exif IFD, each with its values longer than 4 bytes right after it
+            let exif_offset = 8 + ifd(&entries, 8).len();
+            entries[exif_pointer].2 = (exif_offset as u32).to_be_bytes().to_vec();
+            let mut tiff = b"MM\0\x2A\0\0\0\x08".to_vec();
+            tiff.extend_from_slice(&ifd(&entries, 8));
+            tiff.extend_from_slice(&ifd(&exif_entries, exif_offset));
+            tiff
+        }
+    }
+
+    /// A big-endian IFD starting at offset in the TIFF structure, without a next IFD; the
+    /// entries are given sorted by tag as a type and the bytes of their value
+    fn ifd(entries: &[(u16, u16, Vec<u8>)], offset: usize) -> Vec<u8> {
+        let mut ifd = Vec::new();
+        let mut values = Vec::new();
+        let values_offset = offset + 2 + entries.len() * 12 + 4;
+        ifd.extend_from_slice(&(entries.len() as u16).to_be_bytes());
+        for (tag, kind, value) in entries {
+            // LONG values count 4 bytes each, the other types used here one
+            let count = if *kind == 4 { value.len() / 4 } else { value.len() };
+            ifd.extend_from_slice(&tag.to_be_bytes());
+            ifd.extend_from_slice(&kind.to_be_bytes());
+            ifd.extend_from_slice(&(count as u32).to_be_bytes());
+            if value.len() <= 4 {
+                let mut inline = value.clone();
+                inline.resize(4, 0);
+                ifd.extend_from_slice(&inline);
+            } else {
+                ifd.extend_from_slice(&((values_offset + values.len()) as u32).to_be_bytes());
+                values.extend_from_slice(value);
+                // values start on a word boundary
+                if values.len() % 2 == 1 {
+                    values.push(0);
+                }
+            }
+        }
+        ifd.extend_from_slice(&0u32.to_be_bytes());
+        ifd.extend_from_slice(&values);
+        ifd
+    }
+
+    /// Encodes the image in the given format with the metadata, if any: PNG files get a text
+    /// chunk per field plus the XMP packet, JPEG files an EXIF and an XMP segment; GIF files
+    /// have no room for it
+    pub fn encode(image: &DynamicImage, format: ImageFormat, metadata: Option<&Metadata>) -> Result<Vec<u8>, EncodingError> {
+        match (format, metadata) {
+            (ImageFormat::Png, Some(metadata)) => encode_png(image, metadata),
+            (ImageFormat::Jpeg, Some(metadata)) => {
+                let jpeg = encode_plain(image, format)?;
+                Ok(add_jpeg_segments(jpeg, metadata))
+            }
+            _ => encode_plain(image, format),
+        }
+    }
+
+    fn encode_plain(image: &DynamicImage, format: ImageFormat) -> Result<Vec<u8>, EncodingError> {
+        let mut bytes = Cursor::new(Vec::new());
+        image.write_to(&mut bytes, ImageOutputFormat::from(format))
+            .map_err(|source| EncodingError::EncodeBytes { format, source })?;
+        Ok(bytes.into_inner())
+    }
+
+    fn encode_png(image: &DynamicImage, metadata: &Metadata) -> Result<Vec<u8>, EncodingError> {
+        let image = image.to_rgba8();
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut bytes, image.width(), image.height());
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            // tEXt only holds Latin-1, iTXt is UTF-8
+            for (keyword, text) in metadata.fields() {
+                if text.is_ascii() {
+                    encoder.add_text_chunk(keyword.to_string(), text)?;
+                } else {
+                    encoder.add_itxt_chunk(keyword.to_string(), text)?;
+                }
+            }
+            encoder.add_itxt_chunk(String::from("XML:com.adobe.xmp"), metadata.xmp())?;
+            encoder.write_header()?.write_image_data(image.as_raw())?;
+        }
+        Ok(bytes)
+    }
+
+    /// Inserts the APP1 segments after the start of image marker and the JFIF segment, if any;
+    /// a segment too long for its 16-bit size is left out
+    fn add_jpeg_segments(jpeg: Vec<u8>, metadata: &Metadata) -> Vec<u8> {
+        let mut position = 2;
+        if jpeg.get(2..4) == Some(&[0xFF, 0xE0]) {
+            position += 2 + u16::from_be_bytes([jpeg[4], jpeg[5]]) as usize;
+        }
+        let mut segments = Vec::new();
+        for payload in [[EXIF_HEADER, &metadata.exif()].concat(), [XMP_HEADER, metadata.xmp().as_bytes()].concat()] {
+            if payload.len() + 2 <= u16::MAX as usize {
+                segments.extend_from_slice(&[0xFF, 0xE1]);
+                segments.extend_from_slice(&((payload.len() + 2) as u16).to_be_bytes());
+                segments.extend_from_slice(&payload);
+            }
+        }
+        [&jpeg[..position], &segments, &jpeg[position..]].concat()
+    }
+
+    fn escape(text: &str) -> String {
+        text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use chrono::Local;
+        use image::{DynamicImage, ImageFormat, RgbaImage};
+        use super::{encode, Caption, Metadata, EXIF_HEADER, SOFTWARE, XMP_HEADER};
+
+        fn metadata(title: &str) -> Metadata {
+            Metadata {
+                caption: Caption { title: title.to_string(), description: String::from("A test capture") },
+                captured_at: Some(Local::now()),
+                screen: Some(1),
+                capture_size: Some((8, 6)),
+                edits: vec![String::from("Rotated 90° clockwise")],
+            }
+        }
+
+        fn image() -> DynamicImage {
+            DynamicImage::ImageRgba8(RgbaImage::from_pixel(8, 6, image::Rgba([10, 20, 30, 255])))
+        }
+
+        fn u16_at(bytes: &[u8], at: usize) -> usize {
+            u16::from_be_bytes([bytes[at], bytes[at + 1]]) as usize
+        }
+
+        fn u32_at(bytes: &[u8], at: usize) -> usize {
+            u32::from_be_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]) as usize
+        }
+
+        /// The tags of the IFD at offset with their value, checking that every value is inside
+        /// the buffer and starts on a word boundary
+        fn ifd_entries(tiff: &[u8], offset: usize) -> Vec<(usize, Vec<u8>)> {
+            let count = u16_at(tiff, offset);
+            let next_ifd = offset + 2 + count * 12;
+            assert_eq!(u32_at(tiff, next_ifd), 0);
+            (offset + 2..next_ifd).step_by(12).map(|entry| {
+                let size = if u16_at(tiff, entry + 2) == 4 { 4 } else { 1 };
+                let length = u32_at(tiff, entry + 4) * size;
+                let value = if length > 4 {
+                    let value_offset = u32_at(tiff, entry + 8);
+                    assert!(value_offset >= next_ifd + 4 && value_offset + length <= tiff.len());
+                    assert_eq!(value_offset % 2, 0);
+                    tiff[value_offset..value_offset + length].to_vec()
+                } else {
+                    tiff[entry + 8..entry + 8 + length].to_vec()
+                };
+                (u16_at(tiff, entry), value)
+            }).collect()
+        }
+
+        #[test]
+        fn exif_entries_point_inside_the_buffer() {
+            let metadata = metadata("Title");
+            let tiff = metadata.exif();
+            assert_eq!(&tiff[..8], b"MM\0\x2A\0\0\0\x08");
+            let entries = ifd_entries(&tiff, 8);
+            let tags: Vec<usize> = entries.iter().map(|(tag, _)| *tag).collect();
+            // description, software, date, Exif IFD, Windows title and Windows description
+            assert_eq!(tags, [0x010E, 0x0131, 0x0132, 0x8769, 0x9C9B, 0x9C9C]);
+            assert_eq!(entries[1].1, [SOFTWARE.as_bytes(), &[0]].concat());
+
+            let exif = ifd_entries(&tiff, u32_at(&entries[3].1, 0));
+            let tags: Vec<usize> = exif.iter().map(|(tag, _)| *tag).collect();
+            assert_eq!(tags, [0x9000, 0x9003, 0x9286]);
+            let date = metadata.captured_at.unwrap().format("%Y:%m:%d %H:%M:%S").to_string();
+            assert_eq!(exif[1].1, [date.as_bytes(), &[0]].concat());
+            // the edit has a degree sign, so the comment is in UCS-2
+            assert!(exif[2].1.starts_with(b"UNICODE\0"));
+            let comment: Vec<u16> = exif[2].1[8..].chunks(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect();
+            let comment = String::from_utf16(&comment).unwrap();
+            assert!(comment.contains("Source: Screen 1\nCapture Size: 8x6\nEdits: Rotated 90° clockwise"));
+            assert!(!comment.contains("Title"));
+        }
+
+        #[test]
+        fn jpeg_segments_follow_the_header() {
+            let jpeg = encode(&image(), ImageFormat::Jpeg, Some(&metadata("Title"))).unwrap();
+            assert_eq!(&jpeg[..2], [0xFF, 0xD8]);
+            let mut position = 2;
+            if jpeg[2..4] == [0xFF, 0xE0] {
+                position += 2 + u16_at(&jpeg, 4);
+            }
+            for header in [EXIF_HEADER, XMP_HEADER] {
+                assert_eq!(&jpeg[position..position + 2], [0xFF, 0xE1]);
+                assert!(jpeg[position + 4..].starts_with(header));
+                position += 2 + u16_at(&jpeg, position + 2);
+            }
+            assert_eq!(jpeg[position], 0xFF);
+            assert_eq!(image::load_from_memory(&jpeg).unwrap().width(), 8);
+        }
+
+        #[test]
+        fn png_text_round_trips() {
+            let bytes = encode(&image(), ImageFormat::Png, Some(&metadata("Écran"))).unwrap();
+            let reader = png::Decoder::new(bytes.as_slice()).read_info().unwrap();
+            let info = reader.info();
+            let latin1 = |keyword: &str| info.uncompressed_latin1_text.iter().find(|chunk| chunk.keyword == keyword).map(|chunk| chunk.text.clone());
+            let utf8 = |keyword: &str| info.utf8_text.iter().find(|chunk| chunk.keyword == keyword).map(|chunk| chunk.get_text().unwrap());
+            assert_eq!(latin1("Description").as_deref(), Some("A test capture"));
+            assert_eq!(latin1("Software").as_deref(), Some(SOFTWARE));
+            assert_eq!(latin1("Capture Size").as_deref(), Some("8x6"));
+            assert_eq!(utf8("Title").as_deref(), Some("Écran"));
+            assert_eq!(utf8("Edits").as_deref(), Some("Rotated 90° clockwise"));
+            assert!(utf8("XML:com.adobe.xmp").unwrap().contains("<dc:title><rdf:Alt><rdf:li xml:lang=\"x-default\">Écran</rdf:li>"));
+            assert_eq!((info.width, info.height), (8, 6));
+        }
+
+        #[test]
+        fn gif_has_no_metadata() {
+            let with = encode(&image(), ImageFormat::Gif, Some(&metadata("Title"))).unwrap();
+            let without = encode(&image(), ImageFormat::Gif, None).unwrap();
+            assert_eq!(with, without);
+        }
+    }
+}
@@ -0,0 +1,296 @@
+#![allow(dead_code)]
+pub mod recovery_module {
+    use std::fs::{self, File, OpenOptions, TryLockError};
+    use std::io::ErrorKind;
+    use std::path::{Path, PathBuf};
+    use image::{DynamicImage, ImageFormat};
+    use serde::{Serialize, Deserialize};
+    use crate::error_module::error_module::{EncodingError, RustyCaptureError, WithPath};
+    use crate::metadata_module::metadata_module::Caption;
+    use crate::screenshots_module::screenshot_module::{Screenshot, ScreenshotState};
+    use crate::settings_module::settings_module::data_dir;
+
+    const INDEX_FILE: &str = "recovery.json";
+    const LOCK_FILE: &str = "session.lock";
+
+    /// How many edits can still be undone in a restored capture
+    pub const RECOVERED_UNDO_STEPS: usize = 5;
+
+    /// A capture with unsaved changes, as given to the autosave
+    pub struct RecoveryDocument {
+        pub title: String,
+        pub caption: Caption,
+        pub screenshot: Screenshot,
+        /// The last edits which can be undone, oldest first
+        pub undo_stack: Vec<Screenshot>,
+    }
+
+    /// A screenshot written by the autosave, its images are files of the session directory
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct RecoverySnapshot {
+        pub image: PathBuf,
+        pub original: PathBuf,
+        pub state: ScreenshotState,
+    }
+
+    impl RecoverySnapshot {
+        pub fn open(&self) -> Result<Screenshot, RustyCaptureError> {
+            let open = |path: &PathBuf| image::open(path).map_err(|source| EncodingError::Decode { path: path.clone(), source });
+            Ok(Screenshot::from_parts(open(&self.image)?, open(&self.original)?, self.state.clone()))
+        }
+
+        fn files(&self) -> [&PathBuf; 2] {
+            [&self.image, &self.original]
+        }
+    }
+
+    /// A capture with unsaved changes written by the autosave
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct RecoveryEntry {
+        pub title: String,
+        pub caption: Caption,
+        pub snapshot: RecoverySnapshot,
+        pub undo_stack: Vec<RecoverySnapshot>,
+    }
+
+    /// The directory where a running instance of the app autosaves, locked as long as it runs so
+    /// that the other instances leave it alone
+    pub struct RecoverySession {
+        pub dir: PathBuf,
+        lock: File,
+    }
+
+    impl RecoverySession {
+        /// Creates and locks a new directory inside the given one, never reusing the one of
+        /// another session
+        pub fn start(base: &Path) -> Result<RecoverySession, RustyCaptureError> {
+            fs::create_dir_all(base).with_path(base)?;
+            let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S-%3f");
+            let mut attempt = 0;
+            let dir = loop {
+                let dir = base.join(format!("session-{}-{}-{}", std::process::id(), stamp, attempt));
+                match fs::create_dir(&dir) {
+                    Ok(()) => break dir,
+                    Err(e) if e.kind() == ErrorKind::AlreadyExists => attempt += 1,
+                    Err(e) => return Err(RustyCaptureError::Io { path: dir, source: e }),
+                }
+            };
+            let lock = lock_session(&dir)?.ok_or_else(|| RustyCaptureError::Io { path: dir.join(LOCK_FILE), source: ErrorKind::WouldBlock.into() })?;
+            Ok(RecoverySession { dir, lock })
+        }
+
+        /// Deletes the directory when the instance closes properly
+        pub fn close(self) -> Result<(), RustyCaptureError> {
+            // an open file cannot be deleted on Windows
+            drop(self.lock);
+            clear_recovery(&self.dir)
+        }
+    }
+
+    /// The captures left by an instance which did not close properly, locked so that a single
+    /// instance offers to restore them
+    pub struct RecoveredSession {
+        pub dir: PathBuf,
+        pub entries: Vec<RecoveryEntry>,
+        lock: File,
+    }
+
+    impl RecoveredSession {
+        /// Deletes the directory once its captures are restored or discarded
+        pub fn discard(self) -> Result<(), RustyCaptureError> {
+            drop(self.lock);
+            clear_recovery(&self.dir)
+        }
+    }
+
+    /// Where the autosave writes the captures with unsaved changes, a directory per running
+    /// instance inside the data directory; without one there is no autosave, since every
+    /// instance deletes its directory when it exits
+    pub fn recovery_dir() -> Option<PathBuf> {
+        data_dir().map(|dir| dir.join("recovery"))
+    }
+
+    /// Locks the directory of a session, None when another instance holds it
+    fn lock_session(dir: &Path) -> Result<Option<File>, RustyCaptureError> {
+        let lock_file = dir.join(LOCK_FILE);
+        let lock = OpenOptions::new().write(true).create(true).truncate(false).open(&lock_file).with_path(&lock_file)?;
+        match lock.try_lock() {
+            Ok(()) => Ok(Some(lock)),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(e)) => Err(RustyCaptureError::Io { path: lock_file, source: e }),
+        }
+    }
+
+    /// Replaces the content of the session directory with the given captures, keeping their
+    /// original image and the state of the screenshot next to the annotated one, along with the
+    /// last edits which can be undone; equal images are written once. The index is written last,
+    /// so an interrupted autosave keeps the previous one
+    pub fn write_recovery(dir: &Path, documents: &[RecoveryDocument]) -> Result<(), RustyCaptureError> {
+        fs::create_dir_all(dir).with_path(dir)?;
+        let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S-%3f").to_string();
+        let mut written = Vec::new();
+        let mut entries = Vec::new();
+        for document in documents {
+            let undo_start = document.undo_stack.len().saturating_sub(RECOVERED_UNDO_STEPS);
+            entries.push(RecoveryEntry {
+                title: document.title.clone(),
+                caption: document.caption.clone(),
+                snapshot: write_snapshot(dir, &stamp, &mut written, &document.screenshot)?,
+                undo_stack: document.undo_stack[undo_start..].iter()
+                    .map(|screenshot| write_snapshot(dir, &stamp, &mut written, screenshot))
+                    .collect::<Result<_, _>>()?,
+            });
+        }
+        let index_file = dir.join(INDEX_FILE);
+        let tmp = dir.join(format!("{}.tmp", INDEX_FILE));
+        fs::write(&tmp, serde_json::to_string_pretty(&entries).with_path(&tmp)?).with_path(&tmp)?;
+        fs::rename(&tmp, &index_file).with_path(&index_file)?;
+
+        // images of the previous autosave
+        for file in fs::read_dir(dir).with_path(dir)?.flatten().map(|entry| entry.path()) {
+            let stale = file.extension().is_some_and(|extension| extension == "png") && !written.iter().any(|(_, written)| *written == file);
+            if stale {
+                fs::remove_file(&file).with_path(&file)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the images of a screenshot, reusing the files of the equal ones already written
+    fn write_snapshot(dir: &Path, stamp: &str, written: &mut Vec<(DynamicImage, PathBuf)>, screenshot: &Screenshot) -> Result<RecoverySnapshot, RustyCaptureError> {
+        let (image, original, state) = screenshot.to_parts();
+        let mut write = |image: DynamicImage| -> Result<PathBuf, RustyCaptureError> {
+            if let Some((_, file)) = written.iter().find(|(other, _)| *other == image) {
+                return Ok(file.clone());
+            }
+            let file = dir.join(format!("image-{}-{}.png", stamp, written.len()));
+            image.save_with_format(&file, ImageFormat::Png)
+                .map_err(|source| EncodingError::Encode { path: file.clone(), format: ImageFormat::Png, source })?;
+            written.push((image, file.clone()));
+            Ok(file)
+        };
+        Ok(RecoverySnapshot { image: write(image)?, original: write(original)?, state })
+    }
+
+    /// Sessions left inside the recovery directory by instances which did not close properly:
+    /// the ones still locked belong to a running instance and are skipped, the ones without any
+    /// capture left are deleted
+    pub fn read_recovery(base: &Path) -> Result<Vec<RecoveredSession>, RustyCaptureError> {
+        let dirs = match fs::read_dir(base) {
+            Ok(dirs) => dirs,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(RustyCaptureError::Io { path: base.to_path_buf(), source: e }),
+        };
+        let mut sessions = Vec::new();
+        for dir in dirs.flatten().map(|entry| entry.path()).filter(|path| path.is_dir()) {
+            let Some(lock) = lock_session(&dir)? else {
+                continue;
+            };
+            let session = RecoveredSession { entries: read_entries(&dir)?, dir, lock };
+            if session.entries.is_empty() {
+                session.discard()?;
+            } else {
+                sessions.push(session);
+            }
+        }
+        Ok(sessions)
+    }
+
+    /// The captures of a session directory, the ones whose images are gone are skipped
+    fn read_entries(dir: &Path) -> Result<Vec<RecoveryEntry>, RustyCaptureError> {
+        let index_file = dir.join(INDEX_FILE);
+        let mut entries: Vec<RecoveryEntry> = match fs::read_to_string(&index_file) {
+            Ok(index) => serde_json::from_str(&index).with_path(&index_file)?,
+            Err(e) if e.kind() == ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(RustyCaptureError::Io { path: index_file, source: e }),
+        };
+        entries.retain(|entry| entry.snapshot.files().iter().all(|file| file.is_file()));
+        for entry in &mut entries {
+            entry.undo_stack.retain(|snapshot| snapshot.files().iter().all(|file| file.is_file()));
+        }
+        Ok(entries)
+    }
+
+    fn clear_recovery(dir: &Path) -> Result<(), RustyCaptureError> {
+        match fs::remove_dir_all(dir) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(RustyCaptureError::Io { path: dir.to_path_buf(), source: e }),
+            _ => Ok(()),
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::{env, fs, process};
+        use std::path::PathBuf;
+        use image::{DynamicImage, Rgba, RgbaImage};
+        use crate::metadata_module::metadata_module::Caption;
+        use crate::screenshots_module::screenshot_module::{Screenshot, Spotlight, SpotlightShape};
+        use super::{read_recovery, write_recovery, RecoveryDocument, RecoverySession};
+
+        fn base_dir(name: &str) -> PathBuf {
+            let dir = env::temp_dir().join(format!("rusty-capture-recovery-{}-{}", process::id(), name));
+            let _ = fs::remove_dir_all(&dir);
+            dir
+        }
+
+        fn document() -> RecoveryDocument {
+            let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(8, 6, Rgba([255, 255, 255, 255])));
+            let before = Screenshot::from_image(image);
+            let mut screenshot = before.clone();
+            screenshot.rotate_dx_90().unwrap();
+            screenshot.add_spotlight(Spotlight { shape: SpotlightShape::Ellipse, start: (1.0, 1.0), end: (4.0, 4.0) });
+            RecoveryDocument {
+                title: String::from("Capture"),
+                caption: Caption { title: String::from("Title"), description: String::new() },
+                screenshot,
+                undo_stack: vec![before],
+            }
+        }
+
+        #[test]
+        fn captures_of_a_crashed_session_come_back_as_they_were() {
+            let base = base_dir("crashed");
+            write_recovery(&base.join("session-1"), &[document()]).unwrap();
+            // the same white image is the original of both snapshots and the annotated image of the first one
+            let images = fs::read_dir(base.join("session-1")).unwrap().flatten().filter(|file| file.path().extension().is_some_and(|extension| extension == "png")).count();
+            assert_eq!(images, 2);
+
+            let sessions = read_recovery(&base).unwrap();
+            assert_eq!(sessions.len(), 1);
+            let entry = &sessions[0].entries[0];
+            assert_eq!(entry.title, "Capture");
+            assert_eq!(entry.caption.title, "Title");
+            let restored = entry.snapshot.open().unwrap();
+            let saved = document().screenshot;
+            assert_eq!(restored.get_image(), saved.get_image());
+            assert_eq!(restored.get_spotlights().len(), 1);
+            assert_eq!(restored.metadata(&Caption::default()).edits, saved.metadata(&Caption::default()).edits);
+            assert_eq!(entry.undo_stack.len(), 1);
+            assert_eq!((restored.get_width(), restored.get_height()), (6, 8));
+            assert_eq!(entry.undo_stack[0].open().unwrap().get_width(), 8);
+
+            for session in sessions {
+                session.discard().unwrap();
+            }
+            assert!(read_recovery(&base).unwrap().is_empty());
+            fs::remove_dir_all(&base).unwrap();
+        }
+
+        #[test]
+        fn sessions_of_running_instances_are_left_alone() {
+            let base = base_dir("running");
+            let running = RecoverySession::start(&base).unwrap();
+            write_recovery(&running.dir, &[document()]).unwrap();
+            assert!(read_recovery(&base).unwrap().is_empty());
+            assert!(running.dir.join("recovery.json").is_file());
+
+            let dir = running.dir.clone();
+            drop(running);
+            // the instance is gone without closing properly
+            assert_eq!(read_recovery(&base).unwrap().len(), 1);
+            RecoverySession::start(&base).unwrap().close().unwrap();
+            assert!(dir.is_dir());
+            fs::remove_dir_all(&base).unwrap();
+        }
+    }
+}
@@ -1,28 +1,168 @@
 #![allow(dead_code)]
 pub mod screenshot_module {
     use std::borrow::Cow;
-    use std::error::Error;
-    use std::path::PathBuf;
+    use std::io::Cursor;
+    use std::path::{Path, PathBuf};
+    use std::sync::OnceLock;
     use std::{cmp, thread};
     use std::time::Duration;
     use arboard::{Clipboard, ImageData};
-    use chrono::Local;
-    use image::{DynamicImage, GenericImage, GenericImageView, ImageFormat, Rgba, RgbaImage};
-    use imageproc::drawing::{draw_line_segment_mut, draw_hollow_rect_mut, draw_text_mut, draw_hollow_circle_mut, draw_polygon_mut};
+    use chrono::{Local, TimeZone};
+    use image::{DynamicImage, GenericImage, GenericImageView, ImageFormat, ImageOutputFormat, Rgba, RgbaImage, imageops};
+    use image::imageops::FilterType;
+    use imageproc::drawing::{draw_line_segment_mut, draw_hollow_rect_mut, draw_text_mut, draw_hollow_circle_mut, draw_polygon_mut, draw_filled_circle_mut, draw_filled_rect_mut, text_size};
+    use imageproc::geometric_transformations::{rotate_about_center, Interpolation};
     use imageproc::point::Point;
     use imageproc::rect::Rect;
     use screenshots::Screen;
-    use thiserror::Error;
+    use crate::error_module::error_module::{CaptureError, ClipboardError, EditError, EncodingError, RustyCaptureError, WithPath};
+    use crate::metadata_module::metadata_module::{encode, Caption, Metadata};
     use rusttype::{Scale, Font};
+    use serde::{Deserialize, Serialize};
 
-    #[derive(Error, Debug)]
-    enum ScreenShotError {
-        #[error("sizes are not compatible")]
-        ResizeSize,
-        #[error("Path is not a dir")]
-        PathError,
-        #[error("extension error")]
-        ExtensionError,
+    /// What is put on the clipboard when a screenshot is copied
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum ClipboardMode {
+        /// The image itself
+        #[default]
+        Image,
+        /// The saved file, pasted as a file by file managers and as its path by text fields
+        FileReference,
+        /// An <img> tag with the image embedded as a data URI
+        Html,
+        /// A Markdown image link to the saved file
+        Markdown,
+        /// The PNG encoded as a base64 data URI
+        Base64,
+    }
+
+    impl ClipboardMode {
+        pub const ALL: [ClipboardMode; 5] = [ClipboardMode::Image, ClipboardMode::FileReference, ClipboardMode::Html, ClipboardMode::Markdown, ClipboardMode::Base64];
+
+        /// Whether the screenshot has to be saved before being copied this way
+        pub fn needs_file(&self) -> bool {
+            matches!(self, ClipboardMode::FileReference | ClipboardMode::Markdown)
+        }
+    }
+
+    impl std::fmt::Display for ClipboardMode {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                ClipboardMode::Image => write!(f, "Image"),
+                ClipboardMode::FileReference => write!(f, "File"),
+                ClipboardMode::Html => write!(f, "HTML"),
+                ClipboardMode::Markdown => write!(f, "Markdown link"),
+                ClipboardMode::Base64 => write!(f, "Base64 data URI"),
+            }
+        }
+    }
+
+    /// Most pixels an edit can produce, every copy of the image takes 4 bytes per pixel
+    pub const MAX_PIXELS: u64 = 50_000_000;
+
+    /// Refuses the dimensions of an image bigger than MAX_PIXELS
+    fn check_size(width: u64, height: u64) -> Result<(), RustyCaptureError> {
+        if width.saturating_mul(height) > MAX_PIXELS {
+            return Err(EditError::TooLarge { width, height, max: MAX_PIXELS / 1_000_000 }.into());
+        }
+        Ok(())
+    }
+
+    /// The font of the text and of the step markers, embedded in the binary and parsed once
+    fn font() -> &'static Font<'static> {
+        static FONT: OnceLock<Font<'static>> = OnceLock::new();
+        FONT.get_or_init(|| Font::try_from_bytes(include_bytes!("../resources/fonts/ARIALN.TTF")).expect("the embedded font is valid"))
+    }
+
+    /// Font of the text tool, each of them is also a font family of the window, so the text looks
+    /// the same while it is typed
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum TextFont {
+        Sans,
+        Monospace,
+    }
+
+    impl TextFont {
+        fn font(self) -> &'static Font<'static> {
+            match self {
+                TextFont::Sans => font(),
+                TextFont::Monospace => {
+                    // the monospace font of the window, shipped with egui
+                    static MONOSPACE: OnceLock<Font<'static>> = OnceLock::new();
+                    MONOSPACE.get_or_init(|| {
+                        let data = eframe::egui::FontDefinitions::default().font_data.remove("Hack").expect("egui ships the Hack font");
+                        Font::try_from_vec(data.font.into_owned()).expect("the font of egui is valid")
+                    })
+                }
+            }
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum MarkerShape {
+        Circle,
+        Square,
+    }
+
+    /// A numbered badge placed with the step marker tool, optionally with a leader arrow
+    /// pointing from the badge to the element it refers to
+    #[derive(Clone, Copy, Debug)]
+    pub struct StepMarker {
+        pub position: (f32, f32),
+        pub leader: Option<(f32, f32)>,
+        pub shape: MarkerShape,
+        pub size: f32,
+        pub fill_color: [u8; 4],
+        pub text_color: [u8; 4],
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum SpotlightShape {
+        Rectangle,
+        Ellipse,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum SpotlightEffect {
+        Darken,
+        Desaturate,
+    }
+
+    /// A region which keeps its full brightness while the rest of the image is dimmed,
+    /// delimited by the rectangle which diagonal goes from start to end
+    #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+    pub struct Spotlight {
+        pub shape: SpotlightShape,
+        pub start: (f32, f32),
+        pub end: (f32, f32),
+    }
+
+    impl Spotlight {
+        pub fn contains(&self, x: f32, y: f32) -> bool {
+            let (x0, x1) = (self.start.0.min(self.end.0), self.start.0.max(self.end.0));
+            let (y0, y1) = (self.start.1.min(self.end.1), self.start.1.max(self.end.1));
+            match self.shape {
+                SpotlightShape::Rectangle => x >= x0 && x <= x1 && y >= y0 && y <= y1,
+                SpotlightShape::Ellipse => {
+                    let (rx, ry) = ((x1 - x0) / 2.0, (y1 - y0) / 2.0);
+                    if rx <= 0.0 || ry <= 0.0 {
+                        return false;
+                    }
+                    let (dx, dy) = ((x - x0 - rx) / rx, (y - y0 - ry) / ry);
+                    dx * dx + dy * dy <= 1.0
+                }
+            }
+        }
+
+        fn map_points<F: Fn((f32, f32)) -> (f32, f32)>(&mut self, f: F) {
+            self.start = f(self.start);
+            self.end = f(self.end);
+        }
     }
 
     #[derive(Clone)]
@@ -30,6 +170,30 @@ pub mod screenshot_module {
         screenshot: DynamicImage,
         original_image: DynamicImage,
         intermediate_image: DynamicImage,
+        step_markers: Vec<StepMarker>,
+        step_markers_base: DynamicImage,
+        /// The base with every pending marker but the last, which is the one being dragged
+        step_markers_below: DynamicImage,
+        committed_steps: u32,
+        spotlights: Vec<Spotlight>,
+        spotlight_effect: SpotlightEffect,
+        spotlight_amount: f32,
+        metadata: Metadata,
+    }
+
+    /// Everything about a screenshot but its images, kept by the autosave along with them
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct ScreenshotState {
+        /// Markers drawn on the image, the next one is numbered after them
+        pub steps: u32,
+        pub spotlights: Vec<Spotlight>,
+        pub spotlight_effect: SpotlightEffect,
+        pub spotlight_amount: f32,
+        /// Seconds since the Unix epoch
+        pub captured_at: Option<i64>,
+        pub screen: Option<usize>,
+        pub capture_size: Option<(u32, u32)>,
+        pub edits: Vec<String>,
     }
 
     impl Screenshot {
@@ -38,15 +202,49 @@ pub mod screenshot_module {
                 screenshot: DynamicImage::new_rgba8(0, 0),
                 original_image: DynamicImage::new_rgba8(0, 0),
                 intermediate_image: DynamicImage::new_rgba8(0, 0),
+                step_markers: Vec::new(),
+                step_markers_base: DynamicImage::new_rgba8(0, 0),
+                step_markers_below: DynamicImage::new_rgba8(0, 0),
+                committed_steps: 0,
+                spotlights: Vec::new(),
+                spotlight_effect: SpotlightEffect::Darken,
+                spotlight_amount: 0.6,
+                metadata: Metadata::default(),
+            }
+        }
+
+        /// Opens an image which does not come from a screen, like one pasted from the clipboard
+        pub fn from_image(image: DynamicImage) -> Screenshot {
+            let image = DynamicImage::from(image.to_rgba8());
+            Screenshot {
+                screenshot: image.clone(),
+                original_image: image.clone(),
+                intermediate_image: image,
+                ..Screenshot::new_empty()
             }
         }
 
-        pub fn new(screen: Screen) -> Result<Screenshot, Box<dyn Error>> {
-            let image_captured = screen.capture()?;
+        /// Opens an image file in any format the image crate decodes
+        pub fn from_path(path: &Path) -> Result<Screenshot, RustyCaptureError> {
+            let image = image::open(path).map_err(|source| EncodingError::Decode { path: path.to_path_buf(), source })?;
+            Ok(Screenshot::from_image(image))
+        }
+
+        /// Opens an encoded image, the format is guessed from its content
+        pub fn from_bytes(bytes: &[u8]) -> Result<Screenshot, RustyCaptureError> {
+            Ok(Screenshot::from_image(image::load_from_memory(bytes).map_err(EncodingError::DecodeBytes)?))
+        }
+
+        pub fn new(screen: Screen) -> Result<Screenshot, RustyCaptureError> {
+            let image_captured = screen.capture()
+                .map_err(|e| CaptureError::Screen { screen: screen.display_info.id, source: e.into() })?;
             let width = image_captured.width();
             let height = image_captured.height();
             let image_rgba = image_captured.rgba().to_owned();
-            let rgba_image = RgbaImage::from_raw(width, height, image_rgba).unwrap();
+            let len = image_rgba.len();
+            log::info!("Captured screen {} ({}x{})", screen.display_info.id, width, height);
+            let rgba_image = RgbaImage::from_raw(width, height, image_rgba)
+                .ok_or(CaptureError::WrongSize { screen: screen.display_info.id, width, height, len })?;
             let image_obj = DynamicImage::from(rgba_image);
             let original_obj = image_obj.clone();
             let intermediate_obj = image_obj.clone();
@@ -55,13 +253,74 @@ pub mod screenshot_module {
                     screenshot: image_obj,
                     original_image: original_obj,
                     intermediate_image: intermediate_obj,
+                    step_markers: Vec::new(),
+                    step_markers_base: DynamicImage::new_rgba8(0, 0),
+                    step_markers_below: DynamicImage::new_rgba8(0, 0),
+                    committed_steps: 0,
+                    spotlights: Vec::new(),
+                    spotlight_effect: SpotlightEffect::Darken,
+                    spotlight_amount: 0.6,
+                    metadata: Metadata {
+                        captured_at: Some(Local::now()),
+                        capture_size: Some((width, height)),
+                        ..Metadata::default()
+                    },
                 }
             )
         }
 
-        pub fn save_image(&self, path: &PathBuf, format: ImageFormat) -> Result<(), Box<dyn Error>> {
-            if path.is_dir() == false {
-                return Err(Box::new(ScreenShotError::PathError));
+        /// The annotated image, the original one and the rest of the screenshot, the step markers
+        /// being part of the annotated image
+        pub fn to_parts(&self) -> (DynamicImage, DynamicImage, ScreenshotState) {
+            let state = ScreenshotState {
+                steps: self.next_step_number() - 1,
+                spotlights: self.spotlights.clone(),
+                spotlight_effect: self.spotlight_effect,
+                spotlight_amount: self.spotlight_amount,
+                captured_at: self.metadata.captured_at.map(|time| time.timestamp()),
+                screen: self.metadata.screen,
+                capture_size: self.metadata.capture_size,
+                edits: self.metadata.edits.clone(),
+            };
+            (self.screenshot.clone(), self.original_image.clone(), state)
+        }
+
+        /// Inverse of to_parts, the step markers can no longer be moved or deleted
+        pub fn from_parts(image: DynamicImage, original: DynamicImage, state: ScreenshotState) -> Screenshot {
+            Screenshot {
+                intermediate_image: image.clone(),
+                screenshot: image,
+                original_image: original,
+                committed_steps: state.steps,
+                spotlights: state.spotlights,
+                spotlight_effect: state.spotlight_effect,
+                spotlight_amount: state.spotlight_amount,
+                metadata: Metadata {
+                    captured_at: state.captured_at.and_then(|seconds| Local.timestamp_opt(seconds, 0).single()),
+                    screen: state.screen,
+                    capture_size: state.capture_size,
+                    edits: state.edits,
+                    ..Metadata::default()
+                },
+                ..Screenshot::new_empty()
+            }
+        }
+
+        /// Records the index of the screen the image was captured from
+        pub fn set_screen(&mut self, screen: usize) {
+            self.metadata.screen = Some(screen);
+        }
+
+        /// What is known about the capture, along with the caption given by the user
+        pub fn metadata(&self, caption: &Caption) -> Metadata {
+            Metadata { caption: caption.clone(), ..self.metadata.clone() }
+        }
+
+        /// Saves the image with a timestamped name in the given directory, with the metadata if
+        /// any, returns the file written
+        pub fn save_image(&self, path: &Path, format: ImageFormat, metadata: Option<&Metadata>) -> Result<PathBuf, RustyCaptureError> {
+            if !path.is_dir() {
+                return Err(RustyCaptureError::NotADirectory(path.to_path_buf()));
             }
             let mut file_name = "screenshot-".to_string();
             file_name.push_str(Local::now().format("%d-%m-%Y-%H-%M-%S_%3f").to_string().as_str());
@@ -77,61 +336,307 @@ pub mod screenshot_module {
                     path_with_file_name = path_with_file_name.with_extension(PathBuf::from("jpg"));
                 }
                 _ => {
-                    return Err(Box::new(ScreenShotError::ExtensionError));
+                    return Err(EncodingError::UnsupportedFormat(format).into());
                 }
             }
-            self.screenshot.save_with_format(path_with_file_name, format)?;
-            return Ok(());
+            let bytes = encode(&self.get_image(), format, metadata)?;
+            std::fs::write(&path_with_file_name, bytes).with_path(&path_with_file_name)?;
+            log::info!("Screenshot saved to {}", path_with_file_name.display());
+            Ok(path_with_file_name)
         }
 
-        pub fn save_to_clipboard(&self) -> Result<(), Box<dyn Error>> {
-            let mut clipboard = Clipboard::new()?;
-            clipboard.set_image(ImageData {
-                width: self.screenshot.width() as usize,
-                height: self.screenshot.height() as usize,
-                bytes: Cow::from(self.screenshot.as_bytes()),
-            })?;
+        /// Copies the image to the clipboard in the given mode, the ones referencing a file need
+        /// the path it was saved to
+        pub fn save_to_clipboard(&self, mode: ClipboardMode, saved: Option<&Path>) -> Result<(), RustyCaptureError> {
+            let mut clipboard = Clipboard::new().map_err(ClipboardError::Unavailable)?;
+            let saved = if mode.needs_file() { saved.ok_or(ClipboardError::NotSaved(mode))? } else { Path::new("") };
+            let copied = match mode {
+                ClipboardMode::Image => {
+                    let image = self.get_image().to_rgba8();
+                    clipboard.set_image(ImageData {
+                        width: image.width() as usize,
+                        height: image.height() as usize,
+                        bytes: Cow::from(image.as_raw()),
+                    })
+                }
+                ClipboardMode::FileReference => clipboard.set().file_list(&[saved]),
+                ClipboardMode::Html => {
+                    let uri = self.data_uri()?;
+                    clipboard.set_html(format!("<img src=\"{}\" alt=\"Screenshot\">", uri), Some(uri))
+                }
+                ClipboardMode::Markdown => {
+                    let name = saved.file_stem().unwrap_or_default().to_string_lossy().replace('[', "\\[").replace(']', "\\]");
+                    clipboard.set_text(format!("![{}]({})", name, file_url(saved)))
+                }
+                ClipboardMode::Base64 => clipboard.set_text(self.data_uri()?),
+            };
+            copied.map_err(|source| ClipboardError::Copy { mode, source })?;
+            log::info!("Screenshot copied to the clipboard as {}", mode);
             Ok(())
         }
 
-        pub fn get_image(&self) -> Result<DynamicImage, Box<dyn Error>> {
-            Ok(self.screenshot.clone())
+        /// Draws an image over the screenshot, scaled to the given size with its top left corner in
+        /// (x, y); it is an annotation, so the eraser removes it
+        pub fn overlay_image(&mut self, image: &DynamicImage, x: i64, y: i64, width: u32, height: u32) -> Result<(), RustyCaptureError> {
+            if width == 0 || height == 0 {
+                return Err(EditError::EmptySelection.into());
+            }
+            let layer = image.resize_exact(width, height, FilterType::Triangle).to_rgba8();
+            let mut canvas = self.screenshot.to_rgba8();
+            imageops::overlay(&mut canvas, &layer, x, y);
+            self.screenshot = DynamicImage::from(canvas);
+            self.intermediate_image = self.screenshot.clone();
+            Ok(())
+        }
+
+        /// The image encoded as PNG in a data URI, to be embedded in HTML or pasted in a browser
+        pub fn data_uri(&self) -> Result<String, RustyCaptureError> {
+            let mut png = Cursor::new(Vec::new());
+            self.get_image().write_to(&mut png, ImageOutputFormat::Png)
+                .map_err(|source| EncodingError::EncodeBytes { format: ImageFormat::Png, source })?;
+            Ok(format!("data:image/png;base64,{}", base64(png.get_ref())))
+        }
+
+        /// Returns the annotated image with the spotlight effect (if any) applied on top of it
+        pub fn get_image(&self) -> DynamicImage {
+            if self.spotlights.is_empty() {
+                return self.screenshot.clone();
+            }
+            self.apply_spotlights()
         }
 
-        pub fn resize_image(&mut self, x: u32, y: u32, height: i32, width: i32) -> Result<(), Box<dyn Error>> {
+        pub fn resize_image(&mut self, x: u32, y: u32, height: i32, width: i32) -> Result<(), RustyCaptureError> {
             if height < 0 || width < 0 || self.screenshot.width() < (x + width as u32) || self.screenshot.height() < (y + height as u32) {
-                return Err(Box::new(ScreenShotError::ResizeSize));
+                return Err(EditError::AreaOutOfBounds {
+                    x, y, width, height,
+                    image_width: self.screenshot.width(),
+                    image_height: self.screenshot.height(),
+                }.into());
             }
+            self.commit_step_markers();
             self.screenshot = self.screenshot.crop(x, y, width as u32, height as u32);
             self.intermediate_image = self.screenshot.clone();
             self.original_image = self.original_image.crop(x, y, width as u32, height as u32);
+            self.metadata.edits.push(format!("Cropped to {}x{} at ({}, {})", width, height, x, y));
+            for spotlight in self.spotlights.iter_mut() {
+                spotlight.map_points(|(px, py)| (px - x as f32, py - y as f32));
+            }
             Ok(())
         }
-        pub fn get_width(&self) -> Result<u32, Box<dyn Error>> {
-            return Ok(self.screenshot.width());
+        /// Finds the region left once the uniform borders are removed, returned as (x, y, width,
+        /// height): each edge has its own color, the one in the middle of its outermost row or
+        /// column, and rows and columns are removed from every side while all their pixels are
+        /// within tolerance (in any channel) from one of these colors. None means that nothing
+        /// but borders is left
+        pub fn trim_bounds(&self, tolerance: u8) -> Option<(u32, u32, u32, u32)> {
+            let image = self.screenshot.to_rgba8();
+            let (width, height) = image.dimensions();
+            if width == 0 || height == 0 {
+                return None;
+            }
+            let colors = [
+                *image.get_pixel(width / 2, 0),
+                *image.get_pixel(width / 2, height - 1),
+                *image.get_pixel(0, height / 2),
+                *image.get_pixel(width - 1, height / 2),
+            ];
+            let close = |pixel: &Rgba<u8>, color: &Rgba<u8>| pixel.0.iter().zip(color.0.iter()).all(|(a, b)| a.abs_diff(*b) <= tolerance);
+            let border = |pixels: Vec<&Rgba<u8>>| colors.iter().any(|color| pixels.iter().all(|pixel| close(pixel, color)));
+            let row = |y: u32, left: u32, right: u32| border((left..right).map(|x| image.get_pixel(x, y)).collect());
+            let column = |x: u32, top: u32, bottom: u32| border((top..bottom).map(|y| image.get_pixel(x, y)).collect());
+            // right and bottom are exclusive; the sides are trimmed again as long as one of them
+            // moves, since a border of another color on one side stops the trim of the others
+            let (mut left, mut top, mut right, mut bottom) = (0, 0, width, height);
+            loop {
+                let before = (left, top, right, bottom);
+                while top < bottom && row(top, left, right) {
+                    top += 1;
+                }
+                while bottom > top && row(bottom - 1, left, right) {
+                    bottom -= 1;
+                }
+                while left < right && column(left, top, bottom) {
+                    left += 1;
+                }
+                while right > left && column(right - 1, top, bottom) {
+                    right -= 1;
+                }
+                if left >= right || top >= bottom {
+                    return None;
+                }
+                if (left, top, right, bottom) == before {
+                    break;
+                }
+            }
+            Some((left, top, right - left, bottom - top))
+        }
+
+        pub fn get_width(&self) -> u32 {
+            self.screenshot.width()
+        }
+
+        pub fn get_height(&self) -> u32 {
+            self.screenshot.height()
         }
 
-        pub fn get_height(&self) -> Result<u32, Box<dyn Error>> {
-            return Ok(self.screenshot.height());
+        /// Applies the same transformation to the annotated and to the original image, moving the
+        /// spotlights with the given point mapping; pending step markers are committed first and the
+        /// result becomes the new intermediate image, so that the next shape is drawn on top of it
+        fn transform<F, M>(&mut self, f: F, map: M)
+            where F: Fn(&DynamicImage) -> DynamicImage,
+                  M: Fn((f32, f32)) -> (f32, f32) {
+            self.commit_step_markers();
+            self.screenshot = f(&self.screenshot);
+            self.original_image = f(&self.original_image);
+            self.intermediate_image = self.screenshot.clone();
+            for spotlight in self.spotlights.iter_mut() {
+                spotlight.map_points(&map);
+            }
         }
 
-        pub fn rotate_sx_90(&mut self) -> Result<(), Box<dyn Error>> {
-            self.screenshot = self.screenshot.rotate90();
-            self.original_image = self.original_image.rotate90();
+        pub fn rotate_sx_90(&mut self) -> Result<(), RustyCaptureError> {
+            let height = self.screenshot.height() as f32;
+            self.transform(|image| image.rotate90(), |(x, y)| (height - y, x));
+            self.metadata.edits.push(String::from("Rotated 90° clockwise"));
             Ok(())
         }
-        pub fn rotate_dx_90(&mut self) -> Result<(), Box<dyn Error>> {
-            self.screenshot = self.screenshot.rotate270();
-            self.original_image = self.original_image.rotate270();
+        pub fn rotate_dx_90(&mut self) -> Result<(), RustyCaptureError> {
+            let width = self.screenshot.width() as f32;
+            self.transform(|image| image.rotate270(), |(x, y)| (y, width - x));
+            self.metadata.edits.push(String::from("Rotated 90° counterclockwise"));
             Ok(())
         }
 
-        pub fn screenshot_after_delay(duration: Duration, screen: Screen) -> Result<Screenshot, Box<dyn Error>> {
+        pub fn rotate_180(&mut self) -> Result<(), RustyCaptureError> {
+            let (width, height) = (self.screenshot.width() as f32, self.screenshot.height() as f32);
+            self.transform(|image| image.rotate180(), |(x, y)| (width - x, height - y));
+            self.metadata.edits.push(String::from("Rotated 180°"));
+            Ok(())
+        }
+
+        pub fn flip_horizontal(&mut self) -> Result<(), RustyCaptureError> {
+            let width = self.screenshot.width() as f32;
+            self.transform(|image| image.fliph(), |(x, y)| (width - x, y));
+            self.metadata.edits.push(String::from("Flipped horizontally"));
+            Ok(())
+        }
+
+        pub fn flip_vertical(&mut self) -> Result<(), RustyCaptureError> {
+            let height = self.screenshot.height() as f32;
+            self.transform(|image| image.flipv(), |(x, y)| (x, height - y));
+            self.metadata.edits.push(String::from("Flipped vertically"));
+            Ok(())
+        }
+
+        /// Rotates the image clockwise by the given angle in degrees, enlarging the canvas so that no
+        /// corner is cut and filling the uncovered area with the given color
+        pub fn rotate(&mut self, degrees: f32, fill: [u8; 4]) -> Result<(), RustyCaptureError> {
+            let theta = degrees.to_radians();
+            let (sin, cos) = (theta.sin().abs(), theta.cos().abs());
+            let (width, height) = (self.screenshot.width() as f32, self.screenshot.height() as f32);
+            let (new_width, new_height) = ((width * cos + height * sin).round(), (width * sin + height * cos).round());
+            let (canvas_width, canvas_height) = (new_width.max(width) as u32, new_height.max(height) as u32);
+            check_size(canvas_width as u64, canvas_height as u64)?;
+            let rotate = |image: &DynamicImage| {
+                // the image is centered on a canvas big enough to contain it both before and after the rotation
+                let mut canvas = RgbaImage::from_pixel(canvas_width, canvas_height, Rgba(fill));
+                imageops::overlay(&mut canvas, &image.to_rgba8(), ((canvas_width - image.width()) / 2) as i64, ((canvas_height - image.height()) / 2) as i64);
+                let rotated = rotate_about_center(&canvas, theta, Interpolation::Bilinear, Rgba(fill));
+                DynamicImage::from(rotated).crop_imm((canvas_width - new_width as u32) / 2, (canvas_height - new_height as u32) / 2, new_width as u32, new_height as u32)
+            };
+            // spotlights are kept axis-aligned, only their center follows the rotation
+            let (sin, cos) = theta.sin_cos();
+            let rotate_center = |(x, y): (f32, f32)| {
+                let (dx, dy) = (x - width / 2.0, y - height / 2.0);
+                (new_width / 2.0 + dx * cos - dy * sin, new_height / 2.0 + dx * sin + dy * cos)
+            };
+            self.commit_step_markers();
+            self.screenshot = rotate(&self.screenshot);
+            self.original_image = rotate(&self.original_image);
+            self.intermediate_image = self.screenshot.clone();
+            for spotlight in self.spotlights.iter_mut() {
+                let center = ((spotlight.start.0 + spotlight.end.0) / 2.0, (spotlight.start.1 + spotlight.end.1) / 2.0);
+                let (cx, cy) = rotate_center(center);
+                spotlight.map_points(|(x, y)| (x - center.0 + cx, y - center.1 + cy));
+            }
+            self.metadata.edits.push(format!("Rotated {}°", degrees));
+            Ok(())
+        }
+
+        /// Scales the image to the given percentage of its current size
+        pub fn scale(&mut self, percentage: f32, filter: FilterType) -> Result<(), RustyCaptureError> {
+            let width = (self.screenshot.width() as f32 * percentage / 100.0).round() as u32;
+            let height = (self.screenshot.height() as f32 * percentage / 100.0).round() as u32;
+            self.resize_to(width, height, filter)
+        }
+
+        /// Resizes the image to exactly the given dimensions, without keeping the aspect ratio
+        pub fn resize_to(&mut self, width: u32, height: u32, filter: FilterType) -> Result<(), RustyCaptureError> {
+            if width == 0 || height == 0 {
+                return Err(EditError::InvalidSize { width, height }.into());
+            }
+            check_size(width as u64, height as u64)?;
+            let sx = width as f32 / self.screenshot.width() as f32;
+            let sy = height as f32 / self.screenshot.height() as f32;
+            self.transform(|image| image.resize_exact(width, height, filter), |(x, y)| (x * sx, y * sy));
+            self.metadata.edits.push(format!("Resized to {}x{}", width, height));
+            Ok(())
+        }
+
+        /// Extends the canvas by the given amount of pixels on each side, filled with the given color
+        pub fn pad(&mut self, left: u32, top: u32, right: u32, bottom: u32, fill: [u8; 4]) -> Result<(), RustyCaptureError> {
+            let width = self.screenshot.width() as u64 + left as u64 + right as u64;
+            let height = self.screenshot.height() as u64 + top as u64 + bottom as u64;
+            check_size(width, height)?;
+            let (width, height) = (width as u32, height as u32);
+            self.transform(|image| {
+                let mut canvas = RgbaImage::from_pixel(width, height, Rgba(fill));
+                imageops::overlay(&mut canvas, &image.to_rgba8(), left as i64, top as i64);
+                DynamicImage::from(canvas)
+            }, |(x, y)| (x + left as f32, y + top as f32));
+            self.metadata.edits.push(format!("Padded by {}, {}, {}, {} pixels", left, top, right, bottom));
+            Ok(())
+        }
+
+        pub fn grayscale(&mut self) -> Result<(), RustyCaptureError> {
+            self.transform(|image| DynamicImage::from(image.grayscale().to_rgba8()), |p| p);
+            self.metadata.edits.push(String::from("Converted to grayscale"));
+            Ok(())
+        }
+
+        pub fn invert(&mut self) -> Result<(), RustyCaptureError> {
+            self.transform(|image| {
+                let mut image = image.clone();
+                image.invert();
+                image
+            }, |p| p);
+            self.metadata.edits.push(String::from("Inverted the colors"));
+            Ok(())
+        }
+
+        /// Brightness is added to every channel (negative values darken), contrast is a percentage
+        /// (negative values reduce it)
+        pub fn brightness_contrast(&mut self, brightness: i32, contrast: f32) -> Result<(), RustyCaptureError> {
+            self.transform(|image| image.brighten(brightness).adjust_contrast(contrast), |p| p);
+            self.metadata.edits.push(format!("Brightness {:+}, contrast {:+}%", brightness, contrast));
+            Ok(())
+        }
+
+        /// Sharpens the image with an unsharp mask of the given blur radius, the threshold is the
+        /// minimum difference from the blurred image for a pixel to be changed
+        pub fn sharpen(&mut self, sigma: f32, threshold: i32) -> Result<(), RustyCaptureError> {
+            self.transform(|image| image.unsharpen(sigma, threshold), |p| p);
+            self.metadata.edits.push(format!("Sharpened with radius {}", sigma));
+            Ok(())
+        }
+
+        pub fn screenshot_after_delay(duration: Duration, screen: Screen) -> Result<Screenshot, RustyCaptureError> {
+            log::debug!("Capturing screen {} in {:?}", screen.display_info.id, duration);
             thread::sleep(duration);
             Screenshot::new(screen)
         }
 
-        pub fn save_intermediate_image(&mut self) -> Result<(), Box<dyn Error>> {
+        pub fn save_intermediate_image(&mut self) -> Result<(), RustyCaptureError> {
             self.intermediate_image = self.screenshot.clone();
             Ok(())
         }
@@ -328,6 +833,11 @@ pub mod screenshot_module {
         pub fn arrow(&mut self, starting_point: (f32, f32), ending_point: (f32, f32), size: f32, color: [u8; 4]) {
             // save the image before any modification
             self.screenshot = self.intermediate_image.clone();
+            self.draw_arrow_over(starting_point, ending_point, size, color);
+        }
+
+        /// Draws the arrow body and head on top of the current image without restoring the intermediate image first
+        fn draw_arrow_over(&mut self, starting_point: (f32, f32), ending_point: (f32, f32), size: f32, color: [u8; 4]) {
             // arrow body
             self.draw_line(starting_point, ending_point, color, size);
             // arrow head
@@ -351,24 +861,486 @@ pub mod screenshot_module {
             }
         }
 
-        pub fn draw_text(&mut self, text: &String, x: f32, y: f32, color: [u8; 3], scale: Scale) {
-            // Load a font.
+        pub fn draw_text(&mut self, text: &String, x: f32, y: f32, color: [u8; 3], scale: Scale, font: TextFont) {
+            let font = font.font();
             let mut dy = 0;
             let lines = text.split("\n");
             for line in lines {
-                let font = Vec::from(include_bytes!("../resources/fonts/ARIALN.TTF") as &[u8]);
-                let font = Font::try_from_vec(font).unwrap();
                 let color_rgba: [u8; 4] = [color[0], color[1], color[2], 255];
                 draw_text_mut(&mut self.screenshot,
                               Rgba::from(color_rgba),
                               x as i32,
                               y as i32 + dy,
                               scale,
-                              &font,
+                              font,
                               line,
                 );
                 dy += scale.y as i32;
             }
         }
+
+        /// Places a copy of the region of the original image (without annotations) which diagonal
+        /// goes between the two source points, enlarged by the given factor and centered in the destination point,
+        /// with a border of the given color around both the source and the copy; if connectors is
+        /// true, two lines join the facing sides of the source region and the copy
+        pub fn magnify(&mut self, source: ((f32, f32), (f32, f32)), destination: (f32, f32), factor: u32, filter: FilterType, color: [u8; 4], connectors: bool) -> Result<(), RustyCaptureError> {
+            let (width, height) = (self.original_image.width() as f32, self.original_image.height() as f32);
+            let (source_start, source_end) = source;
+            let x0 = source_start.0.min(source_end.0).max(0.0);
+            let y0 = source_start.1.min(source_end.1).max(0.0);
+            let x1 = source_start.0.max(source_end.0).min(width);
+            let y1 = source_start.1.max(source_end.1).min(height);
+            if x1 - x0 < 1.0 || y1 - y0 < 1.0 {
+                return Err(EditError::EmptySelection.into());
+            }
+            let (src_width, src_height) = ((x1 - x0) as u32, (y1 - y0) as u32);
+            let (zoomed_width, zoomed_height) = zoomed_size((src_width, src_height), factor, self.original_image.dimensions());
+            let zoomed = self.original_image
+                .crop_imm(x0 as u32, y0 as u32, src_width, src_height)
+                .resize_exact(zoomed_width, zoomed_height, filter);
+            let dst_x0 = destination.0 - zoomed.width() as f32 / 2.0;
+            let dst_y0 = destination.1 - zoomed.height() as f32 / 2.0;
+            let dst_x1 = dst_x0 + zoomed.width() as f32;
+            let dst_y1 = dst_y0 + zoomed.height() as f32;
+            // save the image before any modification
+            self.screenshot = self.intermediate_image.clone();
+            if connectors {
+                // connect the sides facing each other, horizontally or vertically depending on where the copy mostly lies
+                let (dx, dy) = (destination.0 - (x0 + x1) / 2.0, destination.1 - (y0 + y1) / 2.0);
+                let lines = if dx.abs() > dy.abs() {
+                    let (src_x, dst_x) = if dx > 0.0 { (x1, dst_x0) } else { (x0, dst_x1) };
+                    [((src_x, y0), (dst_x, dst_y0)), ((src_x, y1), (dst_x, dst_y1))]
+                } else {
+                    let (src_y, dst_y) = if dy > 0.0 { (y1, dst_y0) } else { (y0, dst_y1) };
+                    [((x0, src_y), (dst_x0, dst_y)), ((x1, src_y), (dst_x1, dst_y))]
+                };
+                for (start, end) in lines {
+                    draw_line_segment_mut(&mut self.screenshot, start, end, Rgba(color));
+                }
+            }
+            imageops::overlay(&mut self.screenshot, &zoomed, dst_x0 as i64, dst_y0 as i64);
+            // borders are drawn as concentric rectangles outside the two regions
+            for i in 1..=2 {
+                let source_rect = Rect::at(x0 as i32 - i, y0 as i32 - i).of_size(src_width + 2 * i as u32, src_height + 2 * i as u32);
+                let zoomed_rect = Rect::at(dst_x0 as i32 - i, dst_y0 as i32 - i).of_size(zoomed.width() + 2 * i as u32, zoomed.height() + 2 * i as u32);
+                draw_hollow_rect_mut(&mut self.screenshot, source_rect, Rgba(color));
+                draw_hollow_rect_mut(&mut self.screenshot, zoomed_rect, Rgba(color));
+            }
+            Ok(())
+        }
+
+        /// Adds a region excluded from the spotlight effect, the image itself is not modified
+        pub fn add_spotlight(&mut self, spotlight: Spotlight) {
+            self.spotlights.push(spotlight);
+        }
+
+        /// Moves the ending point of the last added spotlight, used while it is being dragged
+        pub fn set_last_spotlight_end(&mut self, end: (f32, f32)) {
+            if let Some(spotlight) = self.spotlights.last_mut() {
+                spotlight.end = end;
+            }
+        }
+
+        /// Removes the last added spotlight containing the given point, if any
+        pub fn remove_spotlight_at(&mut self, x: f32, y: f32) -> bool {
+            match self.spotlights.iter().rposition(|s| s.contains(x, y)) {
+                Some(index) => {
+                    self.spotlights.remove(index);
+                    true
+                }
+                None => false,
+            }
+        }
+
+        /// Removes the spotlights with no area, e.g. the ones created by a click without dragging
+        pub fn discard_empty_spotlights(&mut self) {
+            self.spotlights.retain(|s| (s.start.0 - s.end.0).abs() >= 1.0 && (s.start.1 - s.end.1).abs() >= 1.0);
+        }
+
+        pub fn clear_spotlights(&mut self) {
+            self.spotlights.clear();
+        }
+
+        pub fn get_spotlights(&self) -> &Vec<Spotlight> {
+            &self.spotlights
+        }
+
+        pub fn get_spotlight_effect(&self) -> (SpotlightEffect, f32) {
+            (self.spotlight_effect, self.spotlight_amount)
+        }
+
+        /// Sets how the area outside the spotlights is dimmed, amount goes from 0 (unchanged) to 1
+        /// (black or fully grayscale)
+        pub fn set_spotlight_effect(&mut self, effect: SpotlightEffect, amount: f32) {
+            self.spotlight_effect = effect;
+            self.spotlight_amount = amount.clamp(0.0, 1.0);
+        }
+
+        /// Composites the spotlight effect on a copy of the annotated image, darkening or
+        /// desaturating every pixel which is not inside at least one spotlight
+        fn apply_spotlights(&self) -> DynamicImage {
+            let mut image = self.screenshot.to_rgba8();
+            let amount = self.spotlight_amount;
+            for (x, y, pixel) in image.enumerate_pixels_mut() {
+                if self.spotlights.iter().any(|s| s.contains(x as f32, y as f32)) {
+                    continue;
+                }
+                let [r, g, b, a] = pixel.0;
+                let (r, g, b) = (r as f32, g as f32, b as f32);
+                let (r, g, b) = match self.spotlight_effect {
+                    SpotlightEffect::Darken => (r * (1.0 - amount), g * (1.0 - amount), b * (1.0 - amount)),
+                    SpotlightEffect::Desaturate => {
+                        let luma = 0.299 * r + 0.587 * g + 0.114 * b;
+                        (r + (luma - r) * amount, g + (luma - g) * amount, b + (luma - b) * amount)
+                    }
+                };
+                *pixel = Rgba([r as u8, g as u8, b as u8, a]);
+            }
+            DynamicImage::from(image)
+        }
+
+        /// Returns the step markers that can still be deleted, i.e. the ones placed since
+        /// the last time they were committed to the image
+        pub fn get_step_markers(&self) -> &Vec<StepMarker> {
+            &self.step_markers
+        }
+
+        /// Returns the number the next step marker will show
+        pub fn next_step_number(&self) -> u32 {
+            self.committed_steps + self.step_markers.len() as u32 + 1
+        }
+
+        /// Adds a numbered badge to the image, the number is assigned automatically
+        /// continuing from the markers already placed
+        pub fn add_step_marker(&mut self, marker: StepMarker) {
+            if self.step_markers.is_empty() {
+                self.step_markers_base = self.screenshot.clone();
+            }
+            // the markers already placed are drawn, only the new one is missing
+            self.step_markers_below = self.screenshot.clone();
+            self.step_markers.push(marker);
+            self.render_last_step_marker();
+        }
+
+        /// Points the leader arrow of the last placed marker to the given target (None removes it)
+        pub fn set_last_step_marker_leader(&mut self, target: Option<(f32, f32)>) {
+            if let Some(marker) = self.step_markers.last_mut() {
+                marker.leader = target;
+                self.render_last_step_marker();
+            }
+        }
+
+        /// Removes the marker found under the given point (if any) and renumbers the following ones
+        pub fn remove_step_marker_at(&mut self, x: f32, y: f32) -> bool {
+            let index = self.step_markers.iter().rposition(|m| {
+                let half = m.size / 2.0;
+                (x - m.position.0).abs() <= half && (y - m.position.1).abs() <= half
+            });
+            match index {
+                Some(index) => {
+                    self.step_markers.remove(index);
+                    self.render_step_markers();
+                    true
+                }
+                None => false,
+            }
+        }
+
+        /// Redraws every pending marker on top of the image they were placed on
+        fn render_step_markers(&mut self) {
+            if self.step_markers_base.width() == 0 {
+                return;
+            }
+            self.screenshot = self.step_markers_base.clone();
+            let markers = self.step_markers.clone();
+            for (i, marker) in markers.iter().enumerate() {
+                if i + 1 == markers.len() {
+                    self.step_markers_below = self.screenshot.clone();
+                }
+                self.step_marker(marker, self.committed_steps + i as u32 + 1);
+            }
+            self.intermediate_image = self.screenshot.clone();
+        }
+
+        /// Redraws only the last pending marker, on top of the image with the other ones
+        fn render_last_step_marker(&mut self) {
+            let Some(marker) = self.step_markers.last().copied() else {
+                return;
+            };
+            self.screenshot = self.step_markers_below.clone();
+            self.step_marker(&marker, self.next_step_number() - 1);
+            self.intermediate_image = self.screenshot.clone();
+        }
+
+        /// Makes the pending markers part of the image: they can no longer be deleted, but the
+        /// numbering of the next markers continues from them
+        pub fn commit_step_markers(&mut self) {
+            if self.step_markers_base.width() == 0 {
+                return;
+            }
+            self.committed_steps += self.step_markers.len() as u32;
+            self.step_markers.clear();
+            self.step_markers_base = DynamicImage::new_rgba8(0, 0);
+            self.step_markers_below = DynamicImage::new_rgba8(0, 0);
+        }
+
+        /// Draws a single badge of the given size centered in the marker position, filled with the
+        /// marker color and with the number written in the middle; the leader arrow (if any) is drawn
+        /// first so that the badge covers its tail
+        fn step_marker(&mut self, marker: &StepMarker, number: u32) {
+            let (x, y) = marker.position;
+            let radius = (marker.size / 2.0).max(1.0);
+            if let Some(target) = marker.leader {
+                let distance = ((target.0 - x).powf(2.0) + (target.1 - y).powf(2.0)).sqrt();
+                if distance > radius {
+                    self.draw_arrow_over((x, y), target, (marker.size / 10.0).max(1.0), marker.fill_color);
+                }
+            }
+            match marker.shape {
+                MarkerShape::Circle => {
+                    draw_filled_circle_mut(&mut self.screenshot, (x as i32, y as i32), radius as i32, Rgba(marker.fill_color));
+                }
+                MarkerShape::Square => {
+                    let side = (radius * 2.0) as u32;
+                    let rect = Rect::at((x - radius) as i32, (y - radius) as i32).of_size(side.max(1), side.max(1));
+                    draw_filled_rect_mut(&mut self.screenshot, rect, Rgba(marker.fill_color));
+                }
+            }
+            let font = font();
+            let text = number.to_string();
+            let scale = Scale::uniform(radius * 1.4);
+            let (text_width, text_height) = text_size(scale, font, &text);
+            // the digits sit on the baseline and are about as tall as 0.7 times the scale
+            let digits_center = text_height as f32 - scale.y * 0.35;
+            draw_text_mut(&mut self.screenshot,
+                          Rgba(marker.text_color),
+                          x as i32 - text_width / 2,
+                          (y - digits_center) as i32,
+                          scale,
+                          font,
+                          &text,
+            );
+        }
+    }
+
+    /// Size of a region enlarged by the given factor, scaled down with the same aspect ratio when
+    /// it would not fit in bounds, so that a big region is enlarged less rather than filling the memory
+    fn zoomed_size((width, height): (u32, u32), factor: u32, (max_width, max_height): (u32, u32)) -> (u32, u32) {
+        let zoomed_width = width.saturating_mul(factor);
+        let zoomed_height = height.saturating_mul(factor);
+        if zoomed_width <= max_width && zoomed_height <= max_height {
+            return (zoomed_width, zoomed_height);
+        }
+        let shrink = f64::min(max_width as f64 / zoomed_width as f64, max_height as f64 / zoomed_height as f64);
+        (((zoomed_width as f64 * shrink) as u32).max(1), ((zoomed_height as f64 * shrink) as u32).max(1))
+    }
+
+    /// Reads the image on the clipboard, if any
+    pub fn clipboard_image() -> Result<DynamicImage, RustyCaptureError> {
+        let image = Clipboard::new().map_err(ClipboardError::Unavailable)?.get_image().map_err(|_| ClipboardError::NoImage)?;
+        let rgba = RgbaImage::from_raw(image.width as u32, image.height as u32, image.bytes.into_owned())
+            .ok_or(ClipboardError::NoImage)?;
+        Ok(DynamicImage::from(rgba))
+    }
+
+    /// Standard base64 encoding with padding
+    fn base64(bytes: &[u8]) -> String {
+        const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+        for chunk in bytes.chunks(3) {
+            let n = chunk.iter().enumerate().fold(0u32, |n, (i, byte)| n | (*byte as u32) << (16 - 8 * i));
+            for i in 0..4 {
+                if i <= chunk.len() {
+                    out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+                } else {
+                    out.push('=');
+                }
+            }
+        }
+        out
+    }
+
+    /// The file:// URL of an absolute path, every byte but the unreserved characters and the
+    /// separators being percent-encoded
+    pub fn file_url(path: &Path) -> String {
+        let path = path.to_string_lossy().replace('\\', "/");
+        let mut url = String::from(if path.starts_with('/') { "file://" } else { "file:///" });
+        for byte in path.bytes() {
+            match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => url.push(byte as char),
+                _ => url.push_str(&format!("%{:02X}", byte)),
+            }
+        }
+        url
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use image::{DynamicImage, Rgba, RgbaImage};
+        use image::imageops::FilterType;
+        use imageproc::drawing::draw_filled_rect_mut;
+        use imageproc::rect::Rect;
+        use std::path::Path;
+        use super::{base64, file_url, zoomed_size, MarkerShape, Screenshot, Spotlight, SpotlightShape, StepMarker};
+
+        #[test]
+        fn file_urls_are_percent_encoded() {
+            assert_eq!(file_url(Path::new("/home/me/My Shots/a#1 (2).png")), "file:///home/me/My%20Shots/a%231%20%282%29.png");
+            assert_eq!(file_url(Path::new("C:\\Users\\me\\caf\u{e9}.png")), "file:///C:/Users/me/caf%C3%A9.png");
+        }
+
+        fn blank(width: u32, height: u32) -> Screenshot {
+            Screenshot::from_image(DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, Rgba([255, 255, 255, 255]))))
+        }
+
+        /// A white image with a red top left pixel and a spotlight in its top left corner
+        fn marked(width: u32, height: u32) -> Screenshot {
+            let mut image = RgbaImage::from_pixel(width, height, Rgba([255, 255, 255, 255]));
+            image.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+            let mut screenshot = Screenshot::from_image(DynamicImage::ImageRgba8(image));
+            screenshot.add_spotlight(Spotlight { shape: SpotlightShape::Rectangle, start: (0.0, 0.0), end: (10.0, 5.0) });
+            screenshot
+        }
+
+        fn red_at(screenshot: &Screenshot, x: u32, y: u32) -> bool {
+            screenshot.get_image().to_rgba8().get_pixel(x, y).0 == [255, 0, 0, 255]
+        }
+
+        fn spotlight(screenshot: &Screenshot) -> ((f32, f32), (f32, f32)) {
+            let spotlight = screenshot.get_spotlights()[0];
+            (spotlight.start, spotlight.end)
+        }
+
+        fn bordered(background: [u8; 4]) -> RgbaImage {
+            let mut image = RgbaImage::from_pixel(40, 30, Rgba(background));
+            draw_filled_rect_mut(&mut image, Rect::at(10, 5).of_size(15, 12), Rgba([0, 0, 0, 255]));
+            image
+        }
+
+        #[test]
+        fn trim_removes_uniform_borders() {
+            let screenshot = Screenshot::from_image(DynamicImage::ImageRgba8(bordered([255, 255, 255, 255])));
+            assert_eq!(screenshot.trim_bounds(0), Some((10, 5, 15, 12)));
+        }
+
+        #[test]
+        fn trim_uses_the_color_of_each_edge() {
+            let mut image = bordered([255, 255, 255, 255]);
+            // gray bottom border and blue left border, different from the white top
+            draw_filled_rect_mut(&mut image, Rect::at(0, 25).of_size(40, 5), Rgba([128, 128, 128, 255]));
+            draw_filled_rect_mut(&mut image, Rect::at(0, 0).of_size(3, 30), Rgba([0, 0, 255, 255]));
+            let screenshot = Screenshot::from_image(DynamicImage::ImageRgba8(image));
+            assert_eq!(screenshot.trim_bounds(0), Some((10, 5, 15, 12)));
+        }
+
+        #[test]
+        fn trim_tolerates_noise() {
+            let mut image = bordered([250, 250, 250, 255]);
+            image.put_pixel(2, 2, Rgba([255, 244, 250, 255]));
+            image.put_pixel(38, 28, Rgba([243, 255, 247, 255]));
+            let screenshot = Screenshot::from_image(DynamicImage::ImageRgba8(image));
+            assert_eq!(screenshot.trim_bounds(0), Some((2, 2, 37, 27)));
+            assert_eq!(screenshot.trim_bounds(16), Some((10, 5, 15, 12)));
+        }
+
+        #[test]
+        fn trim_of_a_uniform_image_finds_nothing() {
+            let screenshot = Screenshot::from_image(DynamicImage::ImageRgba8(RgbaImage::from_pixel(8, 8, Rgba([9, 9, 9, 255]))));
+            assert_eq!(screenshot.trim_bounds(16), None);
+        }
+
+        #[test]
+        fn quarter_turn_moves_pixels_and_spotlights_together() {
+            let mut screenshot = marked(40, 20);
+            screenshot.rotate_sx_90().unwrap();
+            assert_eq!((screenshot.get_width(), screenshot.get_height()), (20, 40));
+            assert!(red_at(&screenshot, 19, 0));
+            assert_eq!(spotlight(&screenshot), ((20.0, 0.0), (15.0, 10.0)));
+        }
+
+        #[test]
+        fn free_rotation_enlarges_the_canvas_and_moves_spotlight_centers() {
+            let mut screenshot = marked(40, 20);
+            screenshot.rotate(90.0, [0, 0, 0, 255]).unwrap();
+            assert_eq!((screenshot.get_width(), screenshot.get_height()), (20, 40));
+            // same center as after a quarter turn, the size of the spotlight is kept
+            let ((x0, y0), (x1, y1)) = spotlight(&screenshot);
+            assert!(((x0 + x1) / 2.0 - 17.5).abs() < 0.01 && ((y0 + y1) / 2.0 - 5.0).abs() < 0.01);
+            assert_eq!((x1 - x0, y1 - y0), (10.0, 5.0));
+        }
+
+        #[test]
+        fn padding_shifts_pixels_and_spotlights() {
+            let mut screenshot = marked(10, 10);
+            screenshot.pad(1, 2, 3, 4, [0, 0, 255, 255]).unwrap();
+            assert_eq!((screenshot.get_width(), screenshot.get_height()), (14, 16));
+            assert!(red_at(&screenshot, 1, 2));
+            assert_eq!(spotlight(&screenshot), ((1.0, 2.0), (11.0, 7.0)));
+        }
+
+        #[test]
+        fn huge_sizes_are_refused() {
+            let mut screenshot = marked(10, 10);
+            assert!(screenshot.resize_to(20000, 20000, FilterType::Nearest).is_err());
+            assert!(screenshot.pad(u32::MAX, 0, 0, 0, [0, 0, 0, 255]).is_err());
+            assert_eq!((screenshot.get_width(), screenshot.get_height()), (10, 10));
+        }
+
+        fn marker(x: f32, y: f32) -> StepMarker {
+            StepMarker { position: (x, y), leader: None, shape: MarkerShape::Circle, size: 30.0, fill_color: [255, 0, 0, 255], text_color: [255, 255, 255, 255] }
+        }
+
+        #[test]
+        fn zoomed_copy_fits_in_the_image() {
+            assert_eq!(zoomed_size((100, 50), 4, (3840, 2160)), (400, 200));
+            assert_eq!(zoomed_size((3840, 2160), 8, (3840, 2160)), (3840, 2160));
+            assert_eq!(zoomed_size((1000, 200), 8, (3840, 2160)), (3840, 768));
+            assert_eq!(zoomed_size((u32::MAX, 1), 8, (100, 100)), (100, 1));
+        }
+
+        #[test]
+        fn markers_are_renumbered_after_a_deletion() {
+            let mut screenshot = blank(200, 60);
+            for x in [30.0, 100.0, 170.0] {
+                screenshot.add_step_marker(marker(x, 30.0));
+            }
+            assert!(screenshot.remove_step_marker_at(100.0, 30.0));
+            let positions: Vec<(f32, f32)> = screenshot.get_step_markers().iter().map(|marker| marker.position).collect();
+            assert_eq!(positions, [(30.0, 30.0), (170.0, 30.0)]);
+            assert_eq!(screenshot.next_step_number(), 3);
+            // the last badge now shows 2, as if the middle one had never been placed
+            let mut expected = blank(200, 60);
+            expected.add_step_marker(marker(30.0, 30.0));
+            expected.add_step_marker(marker(170.0, 30.0));
+            assert_eq!(screenshot.get_image().to_rgba8(), expected.get_image().to_rgba8());
+        }
+
+        #[test]
+        fn dragging_the_leader_only_changes_the_last_marker() {
+            let mut screenshot = blank(200, 60);
+            screenshot.add_step_marker(marker(30.0, 30.0));
+            screenshot.add_step_marker(marker(100.0, 30.0));
+            screenshot.set_last_step_marker_leader(Some((180.0, 50.0)));
+            screenshot.set_last_step_marker_leader(None);
+            let mut expected = blank(200, 60);
+            expected.add_step_marker(marker(30.0, 30.0));
+            expected.add_step_marker(marker(100.0, 30.0));
+            assert_eq!(screenshot.get_image().to_rgba8(), expected.get_image().to_rgba8());
+        }
+
+        #[test]
+        fn base64_known_vector() {
+            assert_eq!(base64(b"Man"), "TWFu");
+        }
+
+        #[test]
+        fn base64_padding() {
+            assert_eq!(base64(b""), "");
+            assert_eq!(base64(b"M"), "TQ==");
+            assert_eq!(base64(b"Ma"), "TWE=");
+            assert_eq!(base64(b"Many"), "TWFueQ==");
+            assert_eq!(base64(b"Many "), "TWFueSA=");
+            assert_eq!(base64(&[0xFF, 0xEF, 0xBE]), "/+++");
+        }
     }
 }
@@ -1,67 +1,356 @@
 #![allow(dead_code)]
 pub mod settings_module {
-    use std::error::Error;
+    use std::collections::BTreeMap;
+    use std::{env, fs};
+    use std::fmt::{Display, Formatter};
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
+    use std::sync::mpsc::{self, Receiver};
+    use std::thread;
+    use std::time::{Duration, SystemTime};
+    use image::ImageFormat;
     use serde::{Serialize, Deserialize};
-    use serde_json;
-    use global_hotkey::hotkey::Code;
+    use serde_json::{Map, Value};
     use std::str::FromStr;
-    use thiserror::Error;
+    use crate::error_module::error_module::{RustyCaptureError, SettingsError, WithPath};
+    use crate::hotkey_module::hotkey_module::{Accelerator, Action};
+    use crate::log_module::log_module::LogLevel;
+    use crate::screenshots_module::screenshot_module::{ClipboardMode, MarkerShape, TextFont};
 
-    #[derive(Error, Debug)]
-    enum SettingsError {
-        #[error("Path is not a dir")]
-        PathError,
-        #[error("Too Short!")]
-        LengthError,
+    const SETTINGS_FILE: &str = "settings.json";
+
+    /// Version of the settings written by this build, older files are migrated when read
+    pub const SETTINGS_VERSION: u64 = 1;
+
+
+    /// A field of the settings, used to report which one is invalid
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    pub enum SettingsField {
+        Path,
+        Hotkey(Action),
+        Preset(usize),
+    }
+
+    impl Display for SettingsField {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            match self {
+                SettingsField::Path => write!(f, "Path"),
+                SettingsField::Hotkey(action) => write!(f, "{} hotkey", action),
+                SettingsField::Preset(index) => write!(f, "Capture preset {}", index + 1),
+            }
+        }
+    }
+
+    /// Where the value of a setting in use comes from, each one overriding the previous ones
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum Source {
+        Default,
+        File,
+        Environment(String),
+        CommandLine(String),
+    }
+
+    /// Dotted name of every setting with the source of its value
+    pub type Sources = Vec<(String, Source)>;
+
+    impl Display for Source {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Source::Default => write!(f, "default"),
+                Source::File => write!(f, "settings file"),
+                Source::Environment(variable) => write!(f, "environment variable {}", variable),
+                Source::CommandLine(flag) => write!(f, "command line flag {}", flag),
+            }
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum Shape {
+        Rectangle,
+        Circle,
+        Arrow,
+    }
+
+    /// Color and size last used with a tool
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct ToolStyle {
+        pub color: [u8; 3],
+        pub size: f32,
+    }
+
+    impl Default for ToolStyle {
+        fn default() -> ToolStyle {
+            ToolStyle { color: [0, 0, 0], size: 10.0 }
+        }
+    }
+
+    /// State of the editor restored at startup: the style of each tool, the font of the text, the
+    /// look of the step markers, the capture options, the window geometry and the last folder a
+    /// screenshot was saved to
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct EditorState {
+        pub pen: ToolStyle,
+        pub highlighter: ToolStyle,
+        pub eraser: ToolStyle,
+        pub shapes: ToolStyle,
+        pub shape: Shape,
+        pub text: ToolStyle,
+        pub text_font: TextFont,
+        pub step_marker: ToolStyle,
+        pub marker_shape: MarkerShape,
+        pub marker_size: f32,
+        pub marker_leader: bool,
+        pub magnify: ToolStyle,
+        pub timer: usize,
+        pub screen: usize,
+        pub format: String,
+        pub window_size: Option<[f32; 2]>,
+        pub window_pos: Option<[f32; 2]>,
+        pub last_save_dir: String,
     }
 
+    impl Default for EditorState {
+        fn default() -> EditorState {
+            EditorState {
+                pen: ToolStyle::default(),
+                highlighter: ToolStyle { color: [255, 255, 0], size: 15.0 },
+                eraser: ToolStyle::default(),
+                shapes: ToolStyle { color: [255, 0, 0], size: 5.0 },
+                shape: Shape::Rectangle,
+                text: ToolStyle { color: [0, 0, 0], size: 20.0 },
+                text_font: TextFont::Sans,
+                step_marker: ToolStyle { color: [255, 0, 0], size: 10.0 },
+                marker_shape: MarkerShape::Circle,
+                marker_size: 32.0,
+                marker_leader: true,
+                magnify: ToolStyle { color: [255, 0, 0], size: 10.0 },
+                timer: 0,
+                screen: 0,
+                format: String::from("png"),
+                window_size: None,
+                window_pos: None,
+                last_save_dir: String::new(),
+            }
+        }
+    }
+
+    /// A named set of capture options: what is captured and how, and what happens to the result
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct CapturePreset {
+        pub name: String,
+        pub screen: usize,
+        /// Seconds to wait before the capture
+        pub delay: usize,
+        /// Part of the screen to keep as (x, y, width, height), the whole screen if None
+        pub region: Option<[u32; 4]>,
+        /// Extension of the output format: png, jpg or gif
+        pub format: String,
+        pub border: u32,
+        pub border_color: [u8; 3],
+        /// Folder the capture is saved to, the default path if empty
+        pub destination: String,
+        pub save: bool,
+        pub copy_to_clipboard: bool,
+        pub open_in_editor: bool,
+        /// Global hotkey running the preset, none if empty
+        pub hotkey: String,
+    }
+
+    impl Default for CapturePreset {
+        fn default() -> CapturePreset {
+            CapturePreset {
+                name: String::from("New preset"),
+                screen: 0,
+                delay: 0,
+                region: None,
+                format: String::from("png"),
+                border: 0,
+                border_color: [255, 255, 255],
+                destination: String::new(),
+                save: true,
+                copy_to_clipboard: false,
+                open_in_editor: false,
+                hotkey: String::new(),
+            }
+        }
+    }
+
+    /// Every hotkey is stored as a full accelerator such as "Ctrl+Shift+F4", keyed by the action
+    /// it triggers; actions without an entry get their default hotkey, an empty one is not bound.
+    /// Missing fields take their default value, so files written by older builds still load
     #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(default)]
     pub struct Settings {
-        pub quick: String,
-        pub new_screenshot: String,
-        pub save: String,
-        pub pen: String,
-        pub rubber: String,
+        pub version: u64,
+        pub hotkeys: BTreeMap<Action, String>,
         pub path: String,
+        pub clipboard_mode: ClipboardMode,
+        /// Copies every capture and every saved screenshot without pressing the button
+        pub auto_copy: bool,
+        /// How many captures the history keeps on disk, none with 0
+        pub history_limit: usize,
+        /// Seconds between two autosaves of the captures with unsaved changes, never with 0
+        pub autosave_interval: u64,
+        pub log_level: LogLevel,
+        /// Saves the screenshots without the capture details, title and description
+        pub strip_metadata: bool,
+        pub editor: EditorState,
+        pub presets: Vec<CapturePreset>,
     }
 
     impl Default for Settings {
         fn default() -> Settings {
             Settings {
-                quick: String::from("Q"),
-                new_screenshot: String::from("N"),
-                save: String::from("S"),
-                pen: String::from("P"),
-                rubber: String::from("R"),
+                version: SETTINGS_VERSION,
+                hotkeys: BTreeMap::from([
+                    (Action::QuickScreenshot, String::from("Ctrl+Q")),
+                    (Action::NewScreenshot, String::from("Ctrl+N")),
+                    (Action::Save, String::from("Ctrl+S")),
+                    (Action::CopyToClipboard, String::from("Ctrl+C")),
+                    (Action::RotateLeft, String::from("Ctrl+Left")),
+                    (Action::RotateRight, String::from("Ctrl+Right")),
+                    (Action::Crop, String::from("C")),
+                    (Action::Pen, String::from("Ctrl+P")),
+                    (Action::Highlight, String::from("H")),
+                    (Action::Rubber, String::from("Ctrl+R")),
+                    (Action::Shapes, String::from("S")),
+                    (Action::Text, String::from("T")),
+                    (Action::Undo, String::from("Ctrl+Z")),
+                    (Action::Redo, String::from("Ctrl+Y")),
+                    (Action::ZoomIn, String::from("Ctrl+Equal")),
+                    (Action::ZoomOut, String::from("Ctrl+Minus")),
+                    (Action::ZoomReset, String::from("Ctrl+0")),
+                    (Action::SwapColors, String::from("X")),
+                    (Action::PasteNew, String::from("Ctrl+Shift+V")),
+                    (Action::PasteOverlay, String::from("Ctrl+V")),
+                    (Action::Open, String::from("Ctrl+O")),
+                    (Action::History, String::from("Ctrl+H")),
+                    (Action::CloseTab, String::from("Ctrl+W")),
+                    (Action::NextTab, String::from("Ctrl+Tab")),
+                ]),
                 path: String::from("./"),
+                clipboard_mode: ClipboardMode::Image,
+                auto_copy: false,
+                history_limit: 20,
+                autosave_interval: 30,
+                log_level: LogLevel::Info,
+                strip_metadata: false,
+                editor: EditorState::default(),
+                presets: vec![
+                    CapturePreset {
+                        name: String::from("Bug report"),
+                        delay: 3,
+                        save: false,
+                        copy_to_clipboard: true,
+                        ..CapturePreset::default()
+                    },
+                    CapturePreset {
+                        name: String::from("Docs"),
+                        border: 10,
+                        open_in_editor: true,
+                        ..CapturePreset::default()
+                    },
+                ],
             }
         }
     }
 
     impl Settings {
-        pub fn get_quick_hotkey(&self) -> Result<Code, Box<dyn Error>> {
-            let code_str = format!("Key{}", self.quick.to_uppercase());
-            Ok(Code::from_str(&code_str)?)
+        /// Every non-empty hotkey, the ones of the presets included
+        pub fn bound_hotkeys(&self) -> impl Iterator<Item = (Action, &String)> {
+            self.hotkeys.iter()
+                .map(|(action, hotkey)| (*action, hotkey))
+                .chain(self.presets.iter().enumerate().map(|(index, preset)| (Action::Preset(index), &preset.hotkey)))
+                .filter(|(_, hotkey)| !hotkey.trim().is_empty())
+        }
+
+        /// The hotkey of the action, wherever it is stored
+        pub fn hotkey_mut(&mut self, action: Action) -> &mut String {
+            match action {
+                Action::Preset(index) => &mut self.presets[index].hotkey,
+                action => self.hotkeys.entry(action).or_default(),
+            }
         }
 
-        pub fn get_new_screenshot_hotkey(&self) -> Result<Code, Box<dyn Error>> {
-            let code_str = format!("Key{}", self.new_screenshot.to_uppercase());
-            Ok(Code::from_str(&code_str)?)
+        /// Returns the accelerator of every bound action, failing on the first invalid or
+        /// duplicated one
+        pub fn get_hotkeys(&self) -> Result<Vec<(Action, Accelerator)>, RustyCaptureError> {
+            let mut hotkeys: Vec<(Action, Accelerator)> = Vec::new();
+            for (action, hotkey) in self.bound_hotkeys() {
+                let accelerator = Accelerator::from_str(hotkey)
+                    .map_err(|_| SettingsError::HotkeyError(action.to_string(), hotkey.clone()))?;
+                if let Some((other, _)) = hotkeys.iter().find(|(_, used)| *used == accelerator) {
+                    return Err(SettingsError::DuplicateHotkey(other.to_string(), action.to_string(), accelerator.to_string()).into());
+                }
+                hotkeys.push((action, accelerator));
+            }
+            Ok(hotkeys)
         }
 
-        pub fn get_save_hotkey(&self) -> Result<Code, Box<dyn Error>> {
-            let code_str = format!("Key{}", self.save.to_uppercase());
-            Ok(Code::from_str(&code_str)?)
+        /// Checks every hotkey on its own, returning the error of each invalid or duplicated one
+        pub fn hotkey_errors(&self) -> BTreeMap<Action, String> {
+            let mut errors = BTreeMap::new();
+            let mut used: Vec<(Action, Accelerator)> = Vec::new();
+            for (action, hotkey) in self.bound_hotkeys() {
+                match Accelerator::from_str(hotkey) {
+                    Ok(accelerator) => {
+                        if let Some((other, _)) = used.iter().find(|(_, used)| *used == accelerator) {
+                            errors.insert(action, format!("Already used by {}", other));
+                            errors.entry(*other).or_insert(format!("Already used by {}", action));
+                        }
+                        used.push((action, accelerator));
+                    }
+                    Err(e) => {
+                        errors.insert(action, e.to_string());
+                    }
+                }
+            }
+            errors
         }
 
-        pub fn get_pen_hotkey(&self) -> Result<Code, Box<dyn Error>> {
-            let code_str = format!("Key{}", self.pen.to_uppercase());
-            Ok(Code::from_str(&code_str)?)
+        /// Checks every field, returning the reason each invalid one is rejected for
+        pub fn validate(&self) -> BTreeMap<SettingsField, String> {
+            let mut errors: BTreeMap<SettingsField, String> = self.hotkey_errors().into_iter()
+                .map(|(action, error)| (SettingsField::Hotkey(action), error))
+                .collect();
+            if self.path.trim().is_empty() {
+                errors.insert(SettingsField::Path, String::from("The path is empty"));
+            } else if !Path::new(&self.path).is_dir() {
+                errors.insert(SettingsField::Path, format!("\"{}\" is not a directory", self.path));
+            }
+            for (index, preset) in self.presets.iter().enumerate() {
+                let error = if preset.name.trim().is_empty() {
+                    Some(String::from("The name is empty"))
+                } else if ImageFormat::from_extension(&preset.format).is_none() {
+                    Some(format!("\"{}\" is not an image format", preset.format))
+                } else if !preset.destination.is_empty() && !Path::new(&preset.destination).is_dir() {
+                    Some(format!("\"{}\" is not a directory", preset.destination))
+                } else if preset.region.is_some_and(|[_, _, width, height]| width == 0 || height == 0) {
+                    Some(String::from("The region is empty"))
+                } else if !preset.save && !preset.copy_to_clipboard && !preset.open_in_editor {
+                    Some(String::from("The capture is neither saved, copied nor opened"))
+                } else if preset.copy_to_clipboard && !preset.save && self.clipboard_mode.needs_file() {
+                    Some(format!("The capture has to be saved to be copied as {}", self.clipboard_mode))
+                } else {
+                    None
+                };
+                if let Some(error) = error {
+                    errors.insert(SettingsField::Preset(index), error);
+                }
+            }
+            errors
         }
 
-        pub fn get_rubber_hotkey(&self) -> Result<Code, Box<dyn Error>> {
-            let code_str = format!("Key{}", self.rubber.to_uppercase());
-            Ok(Code::from_str(&code_str)?)
+        /// Returns the hotkey the action has in the default settings, empty if it has none
+        pub fn default_hotkey(action: Action) -> String {
+            match action {
+                Action::Preset(_) => String::new(),
+                action => Settings::default().hotkeys.remove(&action).unwrap_or_default(),
+            }
         }
 
         pub fn get_path(&self) -> String {
@@ -69,45 +358,624 @@ pub mod settings_module {
         }
     }
 
-    pub fn read_settings_from_file(filename: String) -> Result<Settings, Box<dyn Error>> {
-        let file = std::fs::File::open(filename);
-        if file.is_ok() {
-            let file = file.unwrap();
-            let reader = std::io::BufReader::new(file);
-            let u = serde_json::from_reader(reader)?;
-            Ok(u)
+    /// MIGRATIONS[n] turns a version n settings file into a version n + 1 one; files written
+    /// before the version field existed are version 0
+    const MIGRATIONS: [fn(&mut Map<String, Value>); SETTINGS_VERSION as usize] = [
+        migrate_single_letter_hotkeys,
+    ];
+
+    /// Brings a settings file of any older version up to SETTINGS_VERSION
+    fn migrate(value: &mut Value, path: &Path) -> Result<(), RustyCaptureError> {
+        let map = value.as_object_mut().ok_or_else(|| SettingsError::FormatError(path.to_path_buf()))?;
+        let version = map.get("version").and_then(Value::as_u64).unwrap_or(0);
+        if version > SETTINGS_VERSION {
+            return Err(SettingsError::VersionError { path: path.to_path_buf(), version }.into());
+        }
+        if version < SETTINGS_VERSION {
+            log::info!("Upgrading {} from version {} to {}", path.display(), version, SETTINGS_VERSION);
+        }
+        for migration in &MIGRATIONS[version as usize..] {
+            migration(map);
+        }
+        map.insert("version".to_string(), Value::from(SETTINGS_VERSION));
+        Ok(())
+    }
+
+    /// Version 0 stored a single letter per hotkey in its own field, always used together with
+    /// Ctrl: each one becomes the corresponding accelerator in the map keyed by action
+    fn migrate_single_letter_hotkeys(map: &mut Map<String, Value>) {
+        let mut hotkeys = match map.remove("hotkeys") {
+            Some(Value::Object(hotkeys)) => hotkeys,
+            _ => Map::new(),
+        };
+        for (field, action) in [
+            ("quick", "quick_screenshot"),
+            ("new_screenshot", "new_screenshot"),
+            ("save", "save"),
+            ("pen", "pen"),
+            ("rubber", "rubber"),
+        ] {
+            if let Some(Value::String(letter)) = map.remove(field) {
+                hotkeys.entry(action).or_insert(Value::String(format!("Ctrl+{}", letter.to_uppercase())));
+            }
+        }
+        if !hotkeys.is_empty() {
+            map.insert("hotkeys".to_string(), Value::Object(hotkeys));
+        }
+    }
+
+    /// Directory holding the configuration: $XDG_CONFIG_HOME/rusty-capture, falling back to
+    /// ~/.config/rusty-capture, or %APPDATA%\rusty-capture on Windows
+    pub fn config_dir() -> Option<PathBuf> {
+        let base = if cfg!(windows) {
+            env::var_os("APPDATA").map(PathBuf::from)
         } else {
-            let sett = Settings::default();
-            write_settings_to_file("settings.json".to_string(), &sett)?;
-            Ok(sett)
+            env::var_os("XDG_CONFIG_HOME")
+                .map(PathBuf::from)
+                .filter(|dir| dir.is_absolute())
+                .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        };
+        base.map(|dir| dir.join("rusty-capture"))
+    }
+
+    /// Directory of what is kept between runs besides the settings, like the capture history:
+    /// $XDG_DATA_HOME/rusty-capture, falling back to ~/.local/share/rusty-capture, or
+    /// %LOCALAPPDATA%\rusty-capture on Windows; None rather than a path relative to the working
+    /// directory when none of them is set
+    pub fn data_dir() -> Option<PathBuf> {
+        let base = if cfg!(windows) {
+            env::var_os("LOCALAPPDATA").map(PathBuf::from)
+        } else {
+            env::var_os("XDG_DATA_HOME")
+                .map(PathBuf::from)
+                .filter(|dir| dir.is_absolute())
+                .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share")))
+        };
+        base.filter(|dir| dir.is_absolute()).map(|dir| dir.join("rusty-capture"))
+    }
+
+    /// Directory of what the app writes about itself, like its log: $XDG_STATE_HOME/rusty-capture,
+    /// falling back to ~/.local/state/rusty-capture, or %LOCALAPPDATA%\rusty-capture on Windows
+    pub fn state_dir() -> Option<PathBuf> {
+        let base = if cfg!(windows) {
+            env::var_os("LOCALAPPDATA").map(PathBuf::from)
+        } else {
+            env::var_os("XDG_STATE_HOME")
+                .map(PathBuf::from)
+                .filter(|dir| dir.is_absolute())
+                .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("state")))
+        };
+        base.filter(|dir| dir.is_absolute()).map(|dir| dir.join("rusty-capture"))
+    }
+
+    /// File the settings are read from and written to: the one given with `--config <file>` or
+    /// RUSTY_CAPTURE_CONFIG if any, settings.json in the config directory otherwise, or next to
+    /// the executable when there is no config directory, never a path relative to the working one
+    pub fn settings_path() -> PathBuf {
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            if arg == "--config" {
+                if let Some(file) = args.next() {
+                    return PathBuf::from(file);
+                }
+            } else if let Some(file) = arg.strip_prefix("--config=") {
+                return PathBuf::from(file);
+            }
+        }
+        if let Some(file) = env::var_os("RUSTY_CAPTURE_CONFIG") {
+            return PathBuf::from(file);
         }
+        config_dir()
+            .or_else(|| env::current_exe().ok().and_then(|exe| exe.parent().map(Path::to_path_buf)))
+            .unwrap_or_else(env::temp_dir)
+            .join(SETTINGS_FILE)
     }
 
-    pub fn write_settings_to_file(filename: String, settings: &Settings) -> Result<(), Box<dyn Error>> {
-        let file = std::fs::File::create(filename)?;
-        let writer = std::io::BufWriter::new(file);
+    /// Reads the settings, creating the file only when it does not exist: a settings.json left in
+    /// the working directory by older versions is moved over, the defaults are used otherwise
+    pub fn read_settings_from_file(path: &Path) -> Result<Settings, RustyCaptureError> {
+        read_settings_and_file(path).map(|(settings, _)| settings)
+    }
+
+    /// Reads the settings like read_settings_from_file, along with the migrated JSON of the file,
+    /// which tells the values it sets
+    fn read_settings_and_file(path: &Path) -> Result<(Settings, Value), RustyCaptureError> {
+        let file = match fs::File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let settings = create_settings_file(path)?;
+                let value = serde_json::to_value(&settings).map_err(SettingsError::from)?;
+                return Ok((settings, value));
+            }
+            // an unreadable file is reported, not replaced with the defaults
+            Err(e) => return Err(RustyCaptureError::Io { path: path.to_path_buf(), source: e }),
+        };
+        let reader = std::io::BufReader::new(file);
+        let mut value: Value = serde_json::from_reader(reader).with_path(path)?;
+        migrate(&mut value, path)?;
+        let mut u: Settings = serde_json::from_value(value.clone()).with_path(path)?;
+        // actions added after the file was written get their default, unless it is taken
+        for (action, hotkey) in Settings::default().hotkeys {
+            if !u.hotkeys.contains_key(&action) {
+                let accelerator = Accelerator::from_str(&hotkey).ok();
+                let taken = u.hotkeys.values().any(|used| Accelerator::from_str(used).ok() == accelerator);
+                u.hotkeys.insert(action, if taken { String::new() } else { hotkey });
+            }
+        }
+        Ok((u, value))
+    }
 
-        //check if path is valid
-        if !std::path::Path::new(&settings.path).exists() {
+    fn create_settings_file(path: &Path) -> Result<Settings, RustyCaptureError> {
+        let legacy = Path::new(SETTINGS_FILE);
+        if path != legacy && legacy.is_file() {
+            log::info!("Moving the settings from {} to {}", legacy.display(), path.display());
+            let sett = read_settings_from_file(legacy)?;
+            write_settings_to_file(path, &sett)?;
+            if let Err(e) = fs::remove_file(legacy) {
+                log::warn!("Could not remove {} after moving it: {}", legacy.display(), e);
+            }
+            Ok(sett)
+        } else {
+            log::info!("Creating {} with the default settings", path.display());
             let sett = Settings::default();
-            serde_json::to_writer(writer, &sett)?;
-            return Err(Box::new(SettingsError::PathError));
+            write_settings_to_file(path, &sett)?;
+            Ok(sett)
         }
+    }
 
+    /// Validates the settings before touching the disk, then replaces the file atomically: they
+    /// are written to a temporary file which is renamed over the old one, kept as a backup
+    pub fn write_settings_to_file(path: &Path, settings: &Settings) -> Result<(), RustyCaptureError> {
+        let errors = settings.validate();
+        if !errors.is_empty() {
+            let report: Vec<String> = errors.iter().map(|(field, error)| format!("{}: {}", field, error)).collect();
+            return Err(SettingsError::InvalidSettings(report.join("\n")).into());
+        }
+
+        let json = serde_json::to_string_pretty(settings).map_err(SettingsError::from)?;
+        replace_file(path, &json, true)?;
+        log::debug!("Settings written to {}", path.display());
+        Ok(())
+    }
 
-        //check if hotkey is at least 1 character long
-        if settings.quick.len() < 1 ||
-            settings.new_screenshot.len() < 1 ||
-            settings.save.len() < 1 ||
-            settings.pen.len() < 1 ||
-            settings.rubber.len() < 1
+    /// Writes the file through a temporary one renamed over it, keeping the old file as a backup
+    /// if asked to
+    fn replace_file(path: &Path, json: &str, backup: bool) -> Result<(), RustyCaptureError> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).with_path(dir)?;
+        }
+        let file_name = path.file_name().ok_or_else(|| SettingsError::FileError(path.to_path_buf()))?.to_string_lossy();
+        let tmp = path.with_file_name(format!("{}.tmp", file_name));
         {
-            let sett = Settings::default();
-            serde_json::to_writer(writer, &sett)?;
-            Err(Box::new(SettingsError::LengthError))
-        } else {
-            serde_json::to_writer(writer, settings)?;
-            Ok(())
+            let mut file = fs::File::create(&tmp).with_path(&tmp)?;
+            file.write_all(json.as_bytes()).and_then(|_| file.sync_all()).with_path(&tmp)?;
+        }
+        if backup && path.is_file() {
+            let backup = path.with_file_name(format!("{}.bak", file_name));
+            fs::copy(path, &backup).with_path(&backup)?;
+        }
+        fs::rename(&tmp, path).with_path(path)
+    }
+
+    /// Replaces only the editor object in the settings file: the other fields are copied as they
+    /// are, without validating them, and the backup of the settings is left alone
+    pub fn write_editor_state(path: &Path, editor: &EditorState) -> Result<(), RustyCaptureError> {
+        let editor = serde_json::to_value(editor).map_err(SettingsError::from)?;
+        let mut value = match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json).with_path(path)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => serde_json::to_value(Settings::default()).map_err(SettingsError::from)?,
+            Err(e) => return Err(RustyCaptureError::Io { path: path.to_path_buf(), source: e }),
+        };
+        let Value::Object(fields) = &mut value else {
+            return Err(SettingsError::FormatError(path.to_path_buf()).into());
+        };
+        fields.insert(String::from("editor"), editor);
+        let json = serde_json::to_string_pretty(&value).map_err(SettingsError::from)?;
+        replace_file(path, &json, false)?;
+        log::debug!("Editor state written to {}", path.display());
+        Ok(())
+    }
+
+    /// Every value of the settings with its dotted name, like "hotkeys.save" or "presets.0.delay",
+    /// lists of plain values like colors are a single value
+    fn leaves(value: &Value, prefix: &str, out: &mut Vec<String>) {
+        let join = |key: &str| if prefix.is_empty() { key.to_string() } else { format!("{}.{}", prefix, key) };
+        match value {
+            Value::Object(map) => map.iter().for_each(|(key, value)| leaves(value, &join(key), out)),
+            Value::Array(list) if list.iter().all(Value::is_object) && !list.is_empty() => list.iter().enumerate().for_each(|(index, value)| leaves(value, &join(&index.to_string()), out)),
+            _ => out.push(prefix.to_string()),
+        }
+    }
+
+    fn pointer(key: &str) -> String {
+        format!("/{}", key.replace('.', "/"))
+    }
+
+    /// The version is managed by the program and the editor state is remembered, not configured
+    fn overridable(key: &str) -> bool {
+        key != "version" && !key.starts_with("editor.")
+    }
+
+    /// Reads the `--<setting> <value>` and `--<setting>=<value>` flags of the command line
+    fn command_line_overrides(mut args: impl Iterator<Item = String>) -> Result<Vec<(String, String)>, RustyCaptureError> {
+        let mut overrides = Vec::new();
+        while let Some(arg) = args.next() {
+            let flag = arg.strip_prefix("--").ok_or_else(|| SettingsError::UnknownOption(arg.clone()))?;
+            let (key, value) = match flag.split_once('=') {
+                Some((key, value)) => (key.to_string(), Some(value.to_string())),
+                None => (flag.to_string(), None),
+            };
+            if key == "print-config" {
+                continue;
+            }
+            let value = match value {
+                Some(value) => value,
+                None => args.next().ok_or_else(|| SettingsError::MissingValue(arg.clone()))?,
+            };
+            if key != "config" {
+                overrides.push((key, value));
+            }
+        }
+        Ok(overrides)
+    }
+
+    /// Replaces a value of the settings with the text given by the user, read as JSON unless the
+    /// value is a string
+    fn set_override(value: &mut Value, key: &str, text: &str, name: &str) -> Result<(), RustyCaptureError> {
+        let field = value.pointer_mut(&pointer(key)).ok_or_else(|| SettingsError::UnknownOption(name.to_string()))?;
+        *field = match field {
+            Value::String(_) => Value::String(text.to_string()),
+            _ => serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.to_string())),
+        };
+        if serde_json::from_value::<Settings>(value.clone()).is_err() {
+            return Err(SettingsError::OverrideError(name.to_string(), text.to_string()).into());
+        }
+        Ok(())
+    }
+
+    /// The settings in use, layered from lowest to highest priority: defaults, settings file,
+    /// RUSTY_CAPTURE_<SETTING> environment variables and `--<setting>` flags, along with where
+    /// every value comes from
+    pub fn resolve_settings(path: &Path) -> Result<(Settings, Sources), RustyCaptureError> {
+        let (settings, file) = read_settings_and_file(path)?;
+        apply_overrides(&settings, &file, |variable| env::var(variable).ok(), env::args().skip(1))
+    }
+
+    /// The settings in use once the given ones are written to the settings file, without reading
+    /// it again
+    pub fn resolve_written_settings(settings: &Settings) -> Result<(Settings, Sources), RustyCaptureError> {
+        let file = serde_json::to_value(settings).map_err(SettingsError::from)?;
+        apply_overrides(settings, &file, |variable| env::var(variable).ok(), env::args().skip(1))
+    }
+
+    /// Layers the environment variables given by var and the command line flags over the
+    /// settings read from file; every action can be set, the ones which are not bound too
+    fn apply_overrides(settings: &Settings, file: &Value, var: impl Fn(&str) -> Option<String>, args: impl Iterator<Item = String>) -> Result<(Settings, Sources), RustyCaptureError> {
+        let mut schema = settings.clone();
+        for action in Action::ALL {
+            schema.hotkey_mut(action);
+        }
+        let mut value = serde_json::to_value(&schema).map_err(SettingsError::from)?;
+        let mut keys = Vec::new();
+        leaves(&value, "", &mut keys);
+        let mut sources: Sources = keys.into_iter()
+            .map(|key| {
+                let source = if file.pointer(&pointer(&key)).is_some() { Source::File } else { Source::Default };
+                (key, source)
+            })
+            .collect();
+        for (key, source) in sources.iter_mut().filter(|(key, _)| overridable(key)) {
+            let variable = format!("RUSTY_CAPTURE_{}", key.to_uppercase().replace('.', "_"));
+            if let Some(text) = var(&variable) {
+                set_override(&mut value, key, &text, &variable)?;
+                log::info!("{} set by {}", key, variable);
+                *source = Source::Environment(variable);
+            }
+        }
+        for (key, text) in command_line_overrides(args)? {
+            let flag = format!("--{}", key);
+            let source = sources.iter_mut()
+                .find(|(name, _)| *name == key && overridable(name))
+                .ok_or_else(|| SettingsError::UnknownOption(flag.clone()))?;
+            set_override(&mut value, &key, &text, &flag)?;
+            log::info!("{} set by {}", key, flag);
+            source.1 = Source::CommandLine(flag);
+        }
+        Ok((serde_json::from_value(value).map_err(SettingsError::from)?, sources))
+    }
+
+    /// Every setting in use with its value and where it comes from, printed by --print-config
+    pub fn print_config(path: &Path) -> Result<String, RustyCaptureError> {
+        let (settings, sources) = resolve_settings(path)?;
+        let value = serde_json::to_value(settings).map_err(SettingsError::from)?;
+        let mut out = format!("# settings file: {}\n", path.display());
+        for (key, source) in sources {
+            let setting = value.pointer(&pointer(&key)).cloned().unwrap_or(Value::Null);
+            out.push_str(&format!("{} = {}  # {}\n", key, setting, source));
+        }
+        Ok(out)
+    }
+
+    /// Values which only make sense on the machine they were written on, left out of exports
+    const MACHINE_SPECIFIC: [&str; 4] = ["window_size", "window_pos", "last_save_dir", "screen"];
+
+    fn home_dir() -> Option<PathBuf> {
+        env::var_os(if cfg!(windows) { "USERPROFILE" } else { "HOME" }).map(PathBuf::from)
+    }
+
+    /// Writes a path inside the home directory as "~/...", so it works for every user
+    fn relativize(path: &str) -> String {
+        match home_dir().and_then(|home| Path::new(path).strip_prefix(home).ok().map(Path::to_path_buf)) {
+            Some(relative) => Path::new("~").join(relative).to_string_lossy().into_owned(),
+            None => path.to_string(),
+        }
+    }
+
+    /// Inverse of relativize
+    fn expand_home(path: &str) -> String {
+        match (path.strip_prefix('~'), home_dir()) {
+            (Some(""), Some(home)) => home.to_string_lossy().into_owned(),
+            (Some(rest), Some(home)) if rest.starts_with(['/', '\\']) => {
+                home.join(rest.trim_start_matches(['/', '\\'])).to_string_lossy().into_owned()
+            }
+            _ => path.to_string(),
+        }
+    }
+
+    /// Applies f to the save path and to the destination of every preset
+    fn map_paths(value: &mut Value, f: fn(&str) -> String) {
+        if let Some(Value::String(path)) = value.get_mut("path") {
+            *path = f(path);
+        }
+        if let Some(Value::Array(presets)) = value.get_mut("presets") {
+            for preset in presets {
+                if let Some(Value::String(destination)) = preset.get_mut("destination") {
+                    *destination = f(destination);
+                }
+            }
+        }
+    }
+
+    /// Writes the settings to a file which can be shared: paths inside the home directory are
+    /// relative to it and machine-specific values are left out
+    pub fn export_settings(path: &Path, settings: &Settings) -> Result<(), RustyCaptureError> {
+        let mut value = serde_json::to_value(settings).map_err(SettingsError::from)?;
+        map_paths(&mut value, relativize);
+        if let Some(Value::Object(editor)) = value.get_mut("editor") {
+            for field in MACHINE_SPECIFIC {
+                editor.remove(field);
+            }
+        }
+        fs::write(path, serde_json::to_string_pretty(&value).with_path(path)?).with_path(path)?;
+        log::info!("Settings exported to {}", path.display());
+        Ok(())
+    }
+
+    /// Reads settings exported by export_settings (or a whole settings file) on top of the
+    /// current ones: replacing them keeps only the machine-specific values, merging keeps every
+    /// field the file does not have, and presets with the same name are replaced
+    pub fn import_settings(path: &Path, current: &Settings, merge: bool) -> Result<Settings, RustyCaptureError> {
+        log::info!("Importing the settings of {}, {}", path.display(), if merge { "merged" } else { "replacing the current ones" });
+        let mut imported: Value = serde_json::from_str(&fs::read_to_string(path).with_path(path)?).with_path(path)?;
+        migrate(&mut imported, path)?;
+        map_paths(&mut imported, expand_home);
+
+        let current = serde_json::to_value(current).map_err(SettingsError::from)?;
+        let mut base = if merge { current.clone() } else { serde_json::to_value(Settings::default()).map_err(SettingsError::from)? };
+        merge_values(&mut base, imported);
+        if let (Some(Value::Object(editor)), Some(Value::Object(current_editor))) = (base.get_mut("editor"), current.get("editor")) {
+            for field in MACHINE_SPECIFIC {
+                if let Some(value) = current_editor.get(field) {
+                    editor.insert(field.to_string(), value.clone());
+                }
+            }
+        }
+        Ok(serde_json::from_value(base).map_err(SettingsError::from)?)
+    }
+
+    /// Copies every field of the imported value over the base one, going into nested objects;
+    /// presets are matched by name
+    fn merge_values(base: &mut Value, imported: Value) {
+        match (base, imported) {
+            (Value::Object(base), Value::Object(imported)) => {
+                for (key, value) in imported {
+                    match base.get_mut(&key) {
+                        Some(Value::Array(presets)) if key == "presets" => {
+                            if let Value::Array(imported) = value {
+                                for preset in imported {
+                                    match presets.iter_mut().find(|p| p.get("name").is_some() && p.get("name") == preset.get("name")) {
+                                        Some(existing) => *existing = preset,
+                                        None => presets.push(preset),
+                                    }
+                                }
+                            }
+                        }
+                        Some(existing) => merge_values(existing, value),
+                        None => {
+                            base.insert(key, value);
+                        }
+                    }
+                }
+            }
+            (base, imported) => *base = imported,
+        }
+    }
+
+    /// When the settings file was last modified, used to notice changes made by other programs
+    pub fn settings_modified(path: &Path) -> Option<SystemTime> {
+        fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+    }
+
+    /// Watches the settings file from a thread checking when it was modified every second: the new
+    /// time is sent and on_change is called only when it differs from the last one, starting from
+    /// since; the thread stops with the first change after the receiver is dropped
+    pub fn watch_settings(path: PathBuf, since: Option<SystemTime>, on_change: impl Fn() + Send + 'static) -> Receiver<Option<SystemTime>> {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut modified = since;
+            loop {
+                thread::sleep(Duration::from_secs(1));
+                let now = settings_modified(&path);
+                if now != modified {
+                    modified = now;
+                    if sender.send(now).is_err() {
+                        break;
+                    }
+                    on_change();
+                }
+            }
+        });
+        receiver
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::{env, fs, process};
+        use std::path::Path;
+        use serde_json::{json, Value};
+        use crate::error_module::error_module::{RustyCaptureError, SettingsError};
+        use crate::hotkey_module::hotkey_module::Action;
+        use super::{apply_overrides, expand_home, home_dir, import_settings, merge_values, migrate, relativize, CapturePreset, Settings, Source, SETTINGS_VERSION};
+
+        /// Writes the JSON to a file of the temporary directory for this test only
+        fn temp_file(name: &str, value: &Value) -> std::path::PathBuf {
+            let path = env::temp_dir().join(format!("rusty-capture-{}-{}.json", process::id(), name));
+            fs::write(&path, value.to_string()).unwrap();
+            path
+        }
+
+        fn current_settings() -> Settings {
+            let mut settings = Settings { path: String::from("/screenshots"), auto_copy: true, ..Settings::default() };
+            settings.editor.window_size = Some([800.0, 600.0]);
+            settings.editor.screen = 1;
+            settings.presets = vec![CapturePreset { name: String::from("Region"), delay: 1, ..CapturePreset::default() }];
+            settings
+        }
+
+        #[test]
+        fn version_0_file_is_upgraded() {
+            // written by the first releases, before the version field existed
+            let mut value: Value = serde_json::from_str(r#"{"quick":"q","new_screenshot":"n","save":"s","pen":"p","rubber":"r","path":"./"}"#).unwrap();
+            migrate(&mut value, Path::new("settings.json")).unwrap();
+            assert_eq!(value["version"], json!(SETTINGS_VERSION));
+            let settings: Settings = serde_json::from_value(value).unwrap();
+            assert_eq!(settings.hotkeys[&Action::QuickScreenshot], "Ctrl+Q");
+            assert_eq!(settings.hotkeys[&Action::NewScreenshot], "Ctrl+N");
+            assert_eq!(settings.hotkeys[&Action::Save], "Ctrl+S");
+            assert_eq!(settings.hotkeys[&Action::Pen], "Ctrl+P");
+            assert_eq!(settings.hotkeys[&Action::Rubber], "Ctrl+R");
+            assert_eq!(settings.path, "./");
+        }
+
+        #[test]
+        fn current_file_is_left_alone() {
+            let mut value = serde_json::to_value(Settings::default()).unwrap();
+            let before = value.clone();
+            migrate(&mut value, Path::new("settings.json")).unwrap();
+            assert_eq!(value, before);
+        }
+
+        #[test]
+        fn newer_file_is_refused() {
+            let mut value = json!({ "version": SETTINGS_VERSION + 1 });
+            let result = migrate(&mut value, Path::new("settings.json"));
+            assert!(matches!(result, Err(RustyCaptureError::Settings(SettingsError::VersionError { .. }))));
+        }
+
+        /// Resolves the settings of a file setting only the path, with the given variables and flags
+        fn resolve(variables: &[(&str, &str)], args: &[&str]) -> Result<(Settings, Vec<(String, Source)>), RustyCaptureError> {
+            let file = json!({ "version": SETTINGS_VERSION, "path": "/file" });
+            let settings: Settings = serde_json::from_value(file.clone()).unwrap();
+            let var = |name: &str| variables.iter().find(|(variable, _)| *variable == name).map(|(_, value)| value.to_string());
+            apply_overrides(&settings, &file, var, args.iter().map(|arg| arg.to_string()))
+        }
+
+        fn source<'a>(sources: &'a [(String, Source)], key: &str) -> &'a Source {
+            &sources.iter().find(|(name, _)| name == key).unwrap().1
+        }
+
+        #[test]
+        fn command_line_overrides_environment_overrides_file() {
+            let (settings, sources) = resolve(&[("RUSTY_CAPTURE_PATH", "/env"), ("RUSTY_CAPTURE_AUTO_COPY", "true")], &["--path", "/cli"]).unwrap();
+            assert_eq!(settings.path, "/cli");
+            assert!(settings.auto_copy);
+            assert_eq!(source(&sources, "path"), &Source::CommandLine(String::from("--path")));
+            assert_eq!(source(&sources, "auto_copy"), &Source::Environment(String::from("RUSTY_CAPTURE_AUTO_COPY")));
+            assert_eq!(source(&sources, "history_limit"), &Source::Default);
+
+            let (settings, sources) = resolve(&[("RUSTY_CAPTURE_PATH", "/env")], &[]).unwrap();
+            assert_eq!(settings.path, "/env");
+            assert_eq!(source(&sources, "path"), &Source::Environment(String::from("RUSTY_CAPTURE_PATH")));
+
+            let (settings, sources) = resolve(&[], &["--history_limit=5"]).unwrap();
+            assert_eq!(settings.path, "/file");
+            assert_eq!(settings.history_limit, 5);
+            assert_eq!(source(&sources, "path"), &Source::File);
+        }
+
+        #[test]
+        fn unbound_actions_can_be_overridden() {
+            assert!(!Settings::default().hotkeys.contains_key(&Action::Magnify));
+            let (settings, sources) = resolve(&[("RUSTY_CAPTURE_HOTKEYS_MAGNIFY", "Ctrl+M")], &["--hotkeys.spotlight", "Alt+L"]).unwrap();
+            assert_eq!(settings.hotkeys[&Action::Magnify], "Ctrl+M");
+            assert_eq!(settings.hotkeys[&Action::Spotlight], "Alt+L");
+            assert_eq!(source(&sources, "hotkeys.spotlight"), &Source::CommandLine(String::from("--hotkeys.spotlight")));
+        }
+
+        #[test]
+        fn wrong_overrides_are_refused() {
+            assert!(matches!(resolve(&[], &["--no_such_setting", "1"]), Err(RustyCaptureError::Settings(SettingsError::UnknownOption(_)))));
+            assert!(matches!(resolve(&[], &["--history_limit"]), Err(RustyCaptureError::Settings(SettingsError::MissingValue(_)))));
+            assert!(matches!(resolve(&[("RUSTY_CAPTURE_HISTORY_LIMIT", "many")], &[]), Err(RustyCaptureError::Settings(SettingsError::OverrideError(..)))));
+            assert!(matches!(resolve(&[], &["--version", "2"]), Err(RustyCaptureError::Settings(SettingsError::UnknownOption(_)))));
+        }
+
+        #[test]
+        fn merge_replaces_nested_fields_and_presets_by_name() {
+            let mut base = json!({ "path": "a", "editor": { "timer": 1, "screen": 2 }, "presets": [{ "name": "x", "delay": 1 }, { "name": "y", "delay": 2 }] });
+            merge_values(&mut base, json!({ "editor": { "timer": 5 }, "presets": [{ "name": "y", "delay": 9 }, { "name": "z" }] }));
+            assert_eq!(base, json!({ "path": "a", "editor": { "timer": 5, "screen": 2 }, "presets": [{ "name": "x", "delay": 1 }, { "name": "y", "delay": 9 }, { "name": "z" }] }));
+        }
+
+        #[test]
+        fn import_merges_into_the_current_settings() {
+            let path = temp_file("merge", &json!({ "version": SETTINGS_VERSION, "auto_copy": false, "editor": { "window_size": [10.0, 10.0], "timer": 3 }, "presets": [{ "name": "Region", "delay": 5 }, { "name": "Screen" }] }));
+            let imported = import_settings(&path, &current_settings(), true);
+            fs::remove_file(&path).unwrap();
+            let imported = imported.unwrap();
+            assert_eq!(imported.path, "/screenshots");
+            assert!(!imported.auto_copy);
+            assert_eq!(imported.editor.timer, 3);
+            // the size of this window is kept, whatever the file says
+            assert_eq!(imported.editor.window_size, Some([800.0, 600.0]));
+            let presets: Vec<(&str, usize)> = imported.presets.iter().map(|preset| (preset.name.as_str(), preset.delay)).collect();
+            assert_eq!(presets, [("Region", 5), ("Screen", 0)]);
+        }
+
+        #[test]
+        fn import_replaces_all_but_the_machine_specific_values() {
+            let path = temp_file("replace", &json!({ "version": SETTINGS_VERSION, "editor": { "timer": 3 } }));
+            let imported = import_settings(&path, &current_settings(), false);
+            fs::remove_file(&path).unwrap();
+            let imported = imported.unwrap();
+            assert_eq!(imported.path, Settings::default().path);
+            assert_eq!(imported.presets, Settings::default().presets);
+            assert_eq!(imported.editor.timer, 3);
+            assert_eq!(imported.editor.window_size, Some([800.0, 600.0]));
+            assert_eq!(imported.editor.screen, 1);
+        }
+
+        #[test]
+        fn paths_in_the_home_directory_are_relative_to_it() {
+            let Some(home) = home_dir() else { return };
+            let pictures = home.join("Pictures").to_string_lossy().into_owned();
+            let relative = relativize(&pictures);
+            assert_eq!(Path::new(&relative), Path::new("~").join("Pictures"));
+            assert_eq!(expand_home(&relative), pictures);
+            assert_eq!(expand_home("~"), home.to_string_lossy());
+            // outside of the home directory, or only starting like it, nothing changes
+            assert_eq!(relativize("/elsewhere/Pictures"), "/elsewhere/Pictures");
+            assert_eq!(expand_home("~other/Pictures"), "~other/Pictures");
         }
     }
-}
\ No newline at end of file
+}
@@ -1,21 +1,31 @@
 #![allow(dead_code)]
 pub mod state_module {
-    use std::{error::Error, fmt::{Display, Formatter}};
+    use std::fmt::{Display, Formatter};
+    use std::collections::{BTreeMap, HashMap};
+    use chrono::Local;
+    use std::str::FromStr;
     use eframe::egui::Context;
-    use std::time::{Duration, Instant};
+    use std::time::{Duration, Instant, SystemTime};
+    use std::sync::mpsc::Receiver;
     use eframe::egui::Margin;
-    use image::{EncodableLayout, ImageFormat};
-    use crate::screenshots_module::screenshot_module::Screenshot;
+    use image::{DynamicImage, EncodableLayout, ImageFormat};
+    use image::imageops::FilterType;
+    use crate::screenshots_module::screenshot_module::{clipboard_image, ClipboardMode, MarkerShape, Screenshot, Spotlight, SpotlightEffect, SpotlightShape, StepMarker, TextFont};
     use crate::settings_module::settings_module::*;
     use screenshots::Screen;
     use eframe::{egui::{CentralPanel, Layout, Align, TextEdit, Direction, Key, Window, ComboBox, TopBottomPanel, CursorIcon}, App, epaint::{ColorImage, Vec2, Pos2}, egui};
-    use std::{cmp, path::PathBuf};
-    use eframe::egui::{Color32, Frame, Rect, Slider};
+    use std::path::{Path, PathBuf};
+    use eframe::egui::{Button, Color32, Frame, Grid, Rect, Response, Slider};
     use eframe::epaint::Stroke;
     use global_hotkey::GlobalHotKeyEvent;
-    use global_hotkey::hotkey::Modifiers;
+    use global_hotkey::hotkey::{Code, Modifiers};
     use rusttype::Scale;
-    use crate::hotkey_module::hotkey_module::{ActiveShortcuts, HotkeyManager, KeyType};
+    use crate::error_module::error_module::{CaptureError, EditError, RustyCaptureError, WithPath};
+    use crate::metadata_module::metadata_module::{Caption, Metadata};
+    use crate::log_module::log_module::{log_path, recent_log, set_log_level, LogLevel};
+    use crate::hotkey_module::hotkey_module::{Accelerator, Action, HotkeyManager, ShortcutContext};
+    use crate::recovery_module::recovery_module::{read_recovery, recovery_dir, write_recovery, RecoveredSession, RecoveryDocument, RecoverySession, RECOVERED_UNDO_STEPS};
+    use crate::history_module::history_module::{history_dir, saved_screenshots, CaptureSource, History, HistoryEntry};
 
 
     #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -27,6 +37,10 @@ pub mod state_module {
         Text,
         Pause,
         Crop,
+        StepMarker,
+        Magnify,
+        Spotlight,
+        Overlay,
     }
 
     impl Display for DrawingMode {
@@ -39,17 +53,160 @@ pub mod state_module {
                 DrawingMode::Text => write!(f, "Text"),
                 DrawingMode::Pause => write!(f, "Pause"),
                 DrawingMode::Crop => write!(f, "Crop"),
+                DrawingMode::StepMarker => write!(f, "Step Marker"),
+                DrawingMode::Magnify => write!(f, "Magnify"),
+                DrawingMode::Spotlight => write!(f, "Spotlight"),
+                DrawingMode::Overlay => write!(f, "Pasted image"),
             }
         }
     }
 
-    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-    pub enum Shape {
-        Rectangle,
-        Circle,
-        Arrow,
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum AspectRatio {
+        Free,
+        Square,
+        FourThree,
+        SixteenNine,
+        Custom,
+    }
+
+    impl Display for AspectRatio {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            match self {
+                AspectRatio::Free => write!(f, "Free"),
+                AspectRatio::Square => write!(f, "1:1"),
+                AspectRatio::FourThree => write!(f, "4:3"),
+                AspectRatio::SixteenNine => write!(f, "16:9"),
+                AspectRatio::Custom => write!(f, "Custom"),
+            }
+        }
+    }
+
+    /// What the pointer is doing to the pending crop rectangle: resizing it around a fixed corner
+    /// (also used for a brand new rectangle), moving it, or dragging one of its sides
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum CropDrag {
+        Resize { anchor: Pos2 },
+        Move { offset: Vec2 },
+        Side { start: Rect, hx: i8, hy: i8 },
+    }
+
+    /// Image pasted on top of the capture, it can be moved and resized until it is applied
+    pub struct Overlay {
+        pub image: DynamicImage,
+        pub rect: Rect,
+        pub drag: Option<CropDrag>,
+        pub texture: Option<egui::TextureHandle>,
+    }
+
+    /// A capture open in a tab with its own history, zoom and tool; the one of the selected tab
+    /// lives in the fields of ScreenshotStr, the others are kept here
+    pub struct Document {
+        pub title: String,
+        pub screenshot: Screenshot,
+        pub crop_screenshot_tmp: Screenshot,
+        pub undo_stack: Vec<Screenshot>,
+        pub redo_stack: Vec<Screenshot>,
+        pub zoom: f32,
+        pub pan: Vec2,
+        pub drawing_mode: Option<DrawingMode>,
+        pub crop_rect: Option<Rect>,
+        pub crop_drag: Option<CropDrag>,
+        pub magnify_source: Option<((f32, f32), (f32, f32))>,
+        pub overlay: Option<Overlay>,
+        pub last_saved: Option<PathBuf>,
+        /// Format and folder the capture is saved with when saving all the tabs: the ones it was
+        /// last saved with, or the ones selected when it was opened
+        pub target_format: ImageFormat,
+        pub target_dir: PathBuf,
+        pub caption: Caption,
+        /// Edited since it was opened or last saved
+        pub dirty: bool,
+    }
+
+    impl Document {
+        pub fn new(screenshot: Screenshot, title: String, target_format: ImageFormat, target_dir: PathBuf) -> Document {
+            Document {
+                title,
+                crop_screenshot_tmp: screenshot.clone(),
+                screenshot,
+                undo_stack: Vec::new(),
+                redo_stack: Vec::new(),
+                zoom: 1.0,
+                pan: Vec2::ZERO,
+                drawing_mode: None,
+                crop_rect: None,
+                crop_drag: None,
+                magnify_source: None,
+                overlay: None,
+                last_saved: None,
+                target_format,
+                target_dir,
+                caption: Caption::default(),
+                dirty: false,
+            }
+        }
+    }
+
+    /// What is waiting for the user to decide about unsaved changes
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum ClosePrompt {
+        Tab,
+        App,
+    }
+
+    /// What can be done with a capture of the history panel
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum HistoryCommand {
+        Open,
+        Copy,
+        SaveAs(ImageFormat),
+        Delete,
+    }
+
+    /// Values shown in the adjust dialog, kept between two openings of the dialog
+    pub struct AdjustParams {
+        pub angle: f32,
+        pub fill_color: [u8; 3],
+        pub scale_by_percentage: bool,
+        pub percentage: f32,
+        pub width: u32,
+        pub height: u32,
+        pub filter: FilterType,
+        pub padding: [u32; 4],
+        pub brightness: i32,
+        pub contrast: f32,
+        pub sharpen_sigma: f32,
+    }
+
+    impl Default for AdjustParams {
+        fn default() -> Self {
+            Self {
+                angle: 0.0,
+                fill_color: [255, 255, 255],
+                scale_by_percentage: true,
+                percentage: 100.0,
+                width: 0,
+                height: 0,
+                filter: FilterType::Triangle,
+                padding: [0; 4],
+                brightness: 0,
+                contrast: 0.0,
+                sharpen_sigma: 1.0,
+            }
+        }
     }
 
+    /// How many edits can be undone
+    const UNDO_LIMIT: usize = 20;
+
+    /// Lines of the log copied with the diagnostics
+    const DIAGNOSTICS_LOG_LINES: usize = 200;
+
+    /// Difference per channel still counted as the color of a border by Auto-trim, enough for
+    /// JPEG noise and antialiasing
+    const TRIM_TOLERANCE: u8 = 16;
+
     pub struct ScreenshotStr {
         pub timer: usize,
         pub screen: usize,
@@ -69,8 +226,60 @@ pub mod state_module {
         pub shape: Option<Shape>,
         pub tool_color: [u8; 3],
         pub tool_size: f32,
+        pub text_font: TextFont,
+        pub marker_shape: MarkerShape,
+        pub marker_size: f32,
+        pub marker_text_color: [u8; 3],
+        pub marker_leader: bool,
+        pub magnify_source: Option<((f32, f32), (f32, f32))>,
+        pub magnify_factor: u32,
+        pub magnify_smooth: bool,
+        pub magnify_connectors: bool,
+        pub spotlight_shape: SpotlightShape,
+        pub adjust_dialog: bool,
+        pub adjust_params: AdjustParams,
+        pub crop_rect: Option<Rect>,
+        pub crop_drag: Option<CropDrag>,
+        pub crop_aspect: AspectRatio,
+        pub crop_custom_ratio: [f32; 2],
+        pub crop_thirds: bool,
         pub settings_dialog: bool,
         pub settings: Settings,
+        pub recording_hotkey: Option<Action>,
+        pub hotkey_context: ShortcutContext,
+        pub settings_errors: BTreeMap<SettingsField, String>,
+        /// Global hotkeys refused by the OS, with the combination which was tried
+        pub unavailable_hotkeys: BTreeMap<Action, (String, String)>,
+        pub running_preset: Option<CapturePreset>,
+        pub settings_transfer_path: String,
+        pub settings_transfer_merge: bool,
+        pub settings_imported: bool,
+        /// Settings set by the environment or the command line, with where they come from
+        pub settings_overrides: Vec<String>,
+        /// The settings as resolved when read, while the settings window edits the ones in the file
+        pub settings_in_use: Settings,
+        /// File of the settings, found once at startup
+        pub settings_path: PathBuf,
+        pub settings_modified: Option<SystemTime>,
+        pub settings_watcher: Option<Receiver<Option<SystemTime>>>,
+        /// The settings file changed while the settings window was open
+        pub settings_reload_pending: bool,
+        pub editor_state: EditorState,
+        pub saved_editor_state: EditorState,
+        pub editor_changed_at: Option<Instant>,
+        pub styled_mode: Option<DrawingMode>,
+        pub save_dir: String,
+        pub open_dialog: bool,
+        pub open_path: String,
+        pub last_saved: Option<PathBuf>,
+        /// Format and folder of the selected capture, see Document
+        pub target_format: ImageFormat,
+        pub target_dir: PathBuf,
+        pub secondary_color: [u8; 3],
+        pub undo_stack: Vec<Screenshot>,
+        pub redo_stack: Vec<Screenshot>,
+        pub zoom: f32,
+        pub pan: Vec2,
         pub instant: Instant,
         pub starting_point: Option<(f32, f32)>,
         pub upper_panel_size: Vec2,
@@ -80,31 +289,91 @@ pub mod state_module {
         pub window_pos: Pos2,
         pub window_size: Vec2,
         pub crop_screenshot_tmp: Screenshot,
+        pub overlay: Option<Overlay>,
+        pub title: String,
+        pub caption: Caption,
+        pub dirty: bool,
+        pub tabs: Vec<Document>,
+        pub active_tab: usize,
+        pub close_prompt: Option<ClosePrompt>,
+        pub close_confirmed: bool,
+        pub autosave_due: Option<Instant>,
+        pub autosave_thread: Option<std::thread::JoinHandle<()>>,
+        /// Captures left by the sessions which did not close properly, offered to be restored
+        pub recovered: Vec<RecoveredSession>,
+        /// Directory this session autosaves to, None without a data directory
+        pub recovery: Option<RecoverySession>,
+        pub history: History,
+        pub history_panel: bool,
+        pub history_saved: Vec<HistoryEntry>,
+        pub history_thumbnails: HashMap<PathBuf, egui::TextureHandle>,
+        pub history_confirm_delete: Option<PathBuf>,
         pub saved_to_clipboard_dialog: bool,
+        /// What does not work in this session, shown in a banner above the toolbar
+        pub degraded: Vec<String>,
         pub hotkey_manager: HotkeyManager,
     }
 
-    impl Default for ScreenshotStr {
-        fn default() -> Self {
-            let mut tmp = HotkeyManager::new().unwrap();
-            let startup_settings = read_settings_from_file("settings.json".to_string()).unwrap();
-            let key_quick = startup_settings.get_quick_hotkey();
-            let key_screenshot = startup_settings.get_new_screenshot_hotkey();
-            let key_pen = startup_settings.get_pen_hotkey();
-            let key_rubber = startup_settings.get_rubber_hotkey();
-            let key_save = startup_settings.get_save_hotkey();
-
-            tmp.register_new_hotkey(Some(Modifiers::CONTROL), key_screenshot.unwrap(), KeyType::NewScreenshot).unwrap();
-            tmp.register_new_hotkey(Some(Modifiers::CONTROL), key_quick.unwrap(), KeyType::Quick).unwrap();
-            tmp.register_new_hotkey(Some(Modifiers::CONTROL), key_pen.unwrap(), KeyType::Pen).unwrap();
-            tmp.register_new_hotkey(Some(Modifiers::CONTROL), key_rubber.unwrap(), KeyType::Rubber).unwrap();
-            tmp.register_new_hotkey(Some(Modifiers::CONTROL), key_save.unwrap(), KeyType::Save).unwrap();
-            tmp.set_active_shortcuts(ActiveShortcuts::ScreenshotWaiting).unwrap();
+    impl ScreenshotStr {
+        /// The app with the settings resolved at startup from the file at settings_path; it
+        /// starts whatever fails here, telling what is missing
+        pub fn new(settings_path: PathBuf, resolved: Result<(Settings, Sources), RustyCaptureError>) -> Self {
+            let mut degraded = Vec::new();
+            let (startup_settings, sources) = resolved.unwrap_or_else(|e| {
+                degraded.push(format!("The settings could not be read, the defaults are used: {}", e));
+                (Settings::default(), Sources::new())
+            });
+            let mut tmp = HotkeyManager::new().unwrap_or_else(|e| {
+                degraded.push(format!("{}, the hotkeys only work inside the window", e));
+                HotkeyManager::without_global_hotkeys()
+            });
+            match startup_settings.get_hotkeys() {
+                Ok(hotkeys) => {
+                    if let Err(e) = tmp.register_all(hotkeys) {
+                        degraded.push(format!("Some hotkeys are not available: {}", e));
+                    }
+                }
+                Err(e) => degraded.push(format!("The hotkeys are not registered: {}", e)),
+            }
+            match Screen::all() {
+                Ok(screens) if screens.is_empty() => degraded.push(String::from("No screen was detected, images can only be opened or pasted")),
+                Err(e) => degraded.push(format!("The screens could not be listed ({}), images can only be opened or pasted", e)),
+                Ok(_) => {}
+            }
+            let history = match history_dir() {
+                Some(dir) => History::load(&dir).unwrap_or_else(|_| History::new(&dir)),
+                None => {
+                    degraded.push(String::from("No folder was found to keep the app data, the history of the captures is off"));
+                    History::disabled()
+                }
+            };
+            let (recovered, recovery) = match recovery_dir() {
+                Some(dir) => {
+                    // read before the directory of this session is locked
+                    let recovered = read_recovery(&dir).unwrap_or_else(|e| {
+                        log::error!("Could not read the captures to recover: {}", e);
+                        Vec::new()
+                    });
+                    let recovery = match RecoverySession::start(&dir) {
+                        Ok(session) => Some(session),
+                        Err(e) => {
+                            degraded.push(format!("Unsaved captures are not autosaved: {}", e));
+                            None
+                        }
+                    };
+                    (recovered, recovery)
+                }
+                None => {
+                    degraded.push(String::from("No folder was found to keep the app data, unsaved captures are not autosaved"));
+                    (Vec::new(), None)
+                }
+            };
+            let editor = startup_settings.editor.clone();
             Self {
-                timer: 0,
-                screen: 0,
+                timer: editor.timer,
+                screen: if editor.screen < get_screens().len() { editor.screen } else { 0 },
                 screenshot: Screenshot::new_empty(),
-                format: ImageFormat::Png,
+                format: ImageFormat::from_extension(&editor.format).unwrap_or(ImageFormat::Png),
                 color_image: ColorImage::example(),
                 show_image: false,
                 error_dialog: false,
@@ -116,11 +385,57 @@ pub mod state_module {
                 text_edit_dialog: false,
                 text_edit_dialog_position: Pos2::new(0.0, 0.0),
                 text: String::new(),
-                shape: Some(Shape::Rectangle),
+                shape: Some(editor.shape),
                 tool_color: [0, 0, 0],
                 tool_size: 10.0,
+                text_font: editor.text_font,
+                marker_shape: editor.marker_shape,
+                marker_size: editor.marker_size,
+                marker_text_color: [255, 255, 255],
+                marker_leader: editor.marker_leader,
+                magnify_source: None,
+                magnify_factor: 3,
+                magnify_smooth: false,
+                magnify_connectors: true,
+                spotlight_shape: SpotlightShape::Rectangle,
+                adjust_dialog: false,
+                adjust_params: AdjustParams::default(),
+                crop_rect: None,
+                crop_drag: None,
+                crop_aspect: AspectRatio::Free,
+                crop_custom_ratio: [3.0, 2.0],
+                crop_thirds: true,
                 settings_dialog: false,
-                settings: Settings::default(),
+                settings_in_use: startup_settings.clone(),
+                settings: startup_settings,
+                recording_hotkey: None,
+                hotkey_context: ShortcutContext::ScreenshotWaiting,
+                settings_errors: BTreeMap::new(),
+                unavailable_hotkeys: BTreeMap::new(),
+                running_preset: None,
+                settings_transfer_path: settings_path.with_file_name("rusty-capture-settings.json").to_string_lossy().into_owned(),
+                settings_transfer_merge: true,
+                settings_imported: false,
+                settings_overrides: overridden(sources),
+                settings_modified: settings_modified(&settings_path),
+                settings_path,
+                settings_watcher: None,
+                settings_reload_pending: false,
+                editor_state: editor.clone(),
+                saved_editor_state: editor,
+                editor_changed_at: None,
+                styled_mode: None,
+                save_dir: String::new(),
+                open_dialog: false,
+                open_path: String::new(),
+                last_saved: None,
+                target_format: ImageFormat::Png,
+                target_dir: PathBuf::new(),
+                secondary_color: [255, 255, 255],
+                undo_stack: Vec::new(),
+                redo_stack: Vec::new(),
+                zoom: 1.0,
+                pan: Vec2::ZERO,
                 instant: Instant::now(),
                 starting_point: None,
                 upper_panel_size: Vec2::new(0.0, 0.0),
@@ -130,13 +445,29 @@ pub mod state_module {
                 window_pos: Pos2::new(0.0, 0.0),
                 window_size: Vec2::new(0.0, 0.0),
                 crop_screenshot_tmp: Screenshot::new_empty(),
+                overlay: None,
+                title: String::new(),
+                caption: Caption::default(),
+                dirty: false,
+                tabs: Vec::new(),
+                active_tab: 0,
+                close_prompt: None,
+                close_confirmed: false,
+                autosave_due: None,
+                autosave_thread: None,
+                recovered,
+                recovery,
+                history,
+                history_panel: false,
+                history_saved: Vec::new(),
+                history_thumbnails: HashMap::new(),
+                history_confirm_delete: None,
                 saved_to_clipboard_dialog: false,
+                degraded,
                 hotkey_manager: tmp,
             }
         }
-    }
 
-    impl ScreenshotStr {
         //front
         pub fn toggle_drawing_mode(&mut self, mode: DrawingMode) {
             if self.drawing_mode == Some(mode) {
@@ -148,7 +479,7 @@ pub mod state_module {
         }
 
         pub fn convert_image(&mut self) -> () {
-            let image = self.screenshot.get_image().unwrap();
+            let image = self.screenshot.get_image();
             let size = [image.width() as _, image.height() as _];
             let image_buffer = image.to_rgba8();
             let col_im: ColorImage = ColorImage::from_rgba_unmultiplied(
@@ -160,24 +491,31 @@ pub mod state_module {
             self.image_converted = true;
         }
 
+        /// Where the image is shown: fitted to the central panel, then scaled by the zoom around
+        /// the center of the panel and moved by the pan offset
+        pub fn image_rect(&self, available: Vec2, total_window: Vec2) -> Rect {
+            let w = self.screenshot.get_width() as f32;
+            let h = self.screenshot.get_height() as f32;
+            let height = available.y.min(available.x * h / w) * self.zoom;
+            let width = height * w / h;
+            let top = self.upper_panel_size.y + Margin::same(1.0).sum().y;
+            // the history panel takes the left side of the window
+            let center = Pos2::new(total_window.x - available.x / 2.0, top + available.y / 2.0) + self.pan;
+            Rect::from_center_size(center, Vec2::new(width, height))
+        }
+
         pub fn calculate_texture_coordinates(&self, cursor_pos: Pos2, available: Vec2, total_window: Vec2, return_always: bool) -> Option<Pos2> {
-            let w = self.screenshot.get_width().unwrap() as f32;
-            let h = self.screenshot.get_height().unwrap() as f32;
-            //x is 640 out of 640, y is 356 out of 400 (-22*2 equal to borders+top and bottom)
-            let w_window = available.x;
-            let h_window = available.y;
-            let aspect_ratio = w / h;
-            let height = h_window.min(w_window / aspect_ratio);
-            let width = height * aspect_ratio;
-            let h_scale = height / h;
-            let w_scale = width / w;
-            let image_pos_x = (total_window.x - width) / 2.0;
-            let image_pos_y = self.upper_panel_size.y + Margin::same(1.0).sum().y + (h_window - height) / 2.0;
+            let w = self.screenshot.get_width() as f32;
+            let h = self.screenshot.get_height() as f32;
+            let rect = self.image_rect(available, total_window);
             let image_cursor_pos = Pos2 {
-                x: (cursor_pos.x - image_pos_x) / w_scale,
-                y: (cursor_pos.y - image_pos_y) / h_scale,
+                x: (cursor_pos.x - rect.min.x) * w / rect.width(),
+                y: (cursor_pos.y - rect.min.y) * h / rect.height(),
             };
-            if image_cursor_pos.x > w || image_cursor_pos.y > h || image_cursor_pos.y < 0.0 || image_cursor_pos.x < 0.0 {
+            // a zoomed image goes under the panels, only its visible part can be drawn on
+            let top = self.upper_panel_size.y + Margin::same(1.0).sum().y;
+            let hidden = cursor_pos.y < top || cursor_pos.y > top + available.y;
+            if hidden || image_cursor_pos.x > w || image_cursor_pos.y > h || image_cursor_pos.y < 0.0 || image_cursor_pos.x < 0.0 {
                 if !return_always {
                     None
                 } else {
@@ -189,18 +527,10 @@ pub mod state_module {
         }
 
         pub fn calculate_rect_image(&self, available: Vec2, total_window: Vec2) -> (f32, f32, f32, f32, f32, f32) {
-            let w = self.screenshot.get_width().unwrap() as f32;
-            let h = self.screenshot.get_height().unwrap() as f32;
-            //x is 640 out of 640, y is 356 out of 400 (-22*2 equal to borders+top and bottom)
-            let w_window = available.x;
-            let h_window = available.y;
-            let height = h_window.min(w_window * h / w);
-            let width = height * w / h;
-            let h_scale = height / h;
-            let w_scale = width / w;
-            let image_pos_x = (total_window.x - width) / 2.0;
-            let image_pos_y = self.upper_panel_size.y + Margin::same(1.0).sum().y + (h_window - height) / 2.0;
-            return (image_pos_x, image_pos_y, width, height, w_scale, h_scale);
+            let w = self.screenshot.get_width() as f32;
+            let h = self.screenshot.get_height() as f32;
+            let rect = self.image_rect(available, total_window);
+            (rect.min.x, rect.min.y, rect.width(), rect.height(), rect.width() / w, rect.height() / h)
         }
 
         pub fn draw_paint(&mut self, ctx: &Context, available: Vec2, size: f32, color: [u8; 4]) -> bool {
@@ -443,191 +773,1600 @@ pub mod state_module {
             });
         }
 
-        fn conversion(&mut self) {
-            if Instant::now() > self.instant {
-                self.convert_image();
-                self.instant += Duration::from_millis(5);
-            }
+        /// Places a numbered badge where the primary button is pressed; dragging before releasing
+        /// points its leader arrow to the cursor, a secondary click on a badge deletes it
+        pub fn draw_step_marker(&mut self, ctx: &Context, available: Vec2) -> bool {
+            ctx.input(|is| -> bool {
+                let pos = is.pointer.interact_pos();
+                if let Some(pos) = pos {
+                    let texture_coordinates = self.calculate_texture_coordinates(pos, available, ctx.used_size(), false);
+                    if let Some(texture_coordinates) = texture_coordinates {
+                        let x = texture_coordinates.x;
+                        let y = texture_coordinates.y;
+                        if is.pointer.secondary_clicked() {
+                            if self.screenshot.remove_step_marker_at(x, y) {
+                                self.convert_image();
+                            }
+                        } else if is.pointer.primary_down() {
+                            if self.starting_point.is_none() {
+                                self.starting_point = Some((x, y));
+                                self.screenshot.add_step_marker(StepMarker {
+                                    position: (x, y),
+                                    leader: None,
+                                    shape: self.marker_shape,
+                                    size: self.marker_size,
+                                    fill_color: [self.tool_color[0], self.tool_color[1], self.tool_color[2], 255],
+                                    text_color: [self.marker_text_color[0], self.marker_text_color[1], self.marker_text_color[2], 255],
+                                });
+                                self.convert_image();
+                            } else if self.marker_leader {
+                                let start = self.starting_point.unwrap();
+                                let distance = ((x - start.0).powf(2.0) + (y - start.1).powf(2.0)).sqrt();
+                                if distance > self.marker_size / 2.0 {
+                                    self.screenshot.set_last_step_marker_leader(Some((x, y)));
+                                } else {
+                                    self.screenshot.set_last_step_marker_leader(None);
+                                }
+                                self.conversion();
+                            }
+                        } else {
+                            self.starting_point = None;
+                        }
+                        return true;
+                    }
+                }
+                self.starting_point = None;
+                false
+            })
         }
 
-        pub fn check_minimization(&mut self, frame: &mut eframe::Frame) {
-            if self.screenshot_taken {
-                match self.screen_state {
-                    0 => {
-                        frame.set_window_pos(Pos2::new(-200.0, -200.0));
-                        frame.set_window_size(Vec2::new(0.0, 0.0));
-                        if frame.info().window_info.position.unwrap().x == -200.0 && frame.info().window_info.position.unwrap().y == -200.0 {
-                            self.screen_state = 1;
+        /// Dragging selects the region to enlarge, then the enlarged copy follows the cursor
+        /// until a click places it; Escape discards the selected region
+        pub fn draw_magnify(&mut self, ctx: &Context, available: Vec2, color: [u8; 4]) -> bool {
+            ctx.input(|is| -> bool {
+                if is.key_pressed(Key::Escape) {
+                    self.magnify_source = None;
+                    self.starting_point = None;
+                    self.screenshot.rollback_changes();
+                    self.conversion();
+                }
+                let pos = is.pointer.interact_pos();
+                if let Some(pos) = pos {
+                    let texture_coordinates = self.calculate_texture_coordinates(pos, available, ctx.used_size(), false);
+                    if let Some(texture_coordinates) = texture_coordinates {
+                        let x = texture_coordinates.x;
+                        let y = texture_coordinates.y;
+                        match self.magnify_source {
+                            None => {
+                                if is.pointer.primary_down() {
+                                    if self.starting_point.is_none() {
+                                        self.starting_point = Some((x, y));
+                                    } else {
+                                        self.screenshot.rectangle(self.starting_point.unwrap(), (x, y), 2.0, color);
+                                        self.conversion();
+                                    }
+                                } else if let Some(start) = self.starting_point.take() {
+                                    if (start.0 - x).abs() >= 1.0 && (start.1 - y).abs() >= 1.0 {
+                                        self.magnify_source = Some((start, (x, y)));
+                                    }
+                                    self.screenshot.rollback_changes();
+                                    self.conversion();
+                                }
+                            }
+                            Some(source) => {
+                                let filter = if self.magnify_smooth { FilterType::CatmullRom } else { FilterType::Nearest };
+                                let result = self.screenshot.magnify(source, (x, y), self.magnify_factor, filter, color, self.magnify_connectors);
+                                if result.is_ok() && is.pointer.primary_pressed() {
+                                    self.magnify_source = None;
+                                    self.screenshot.save_intermediate_image().unwrap();
+                                    self.convert_image();
+                                } else {
+                                    self.conversion();
+                                }
+                            }
                         }
+                        return true;
                     }
-                    1 => {
-                        let duration = Duration::from_secs(self.timer as u64);
-                        self.screenshot = take_screenshot(duration, self.screen);
-                        self.crop_screenshot_tmp=self.screenshot.clone();
-                        self.starting_point=None;
-                        self.convert_image();
-                        self.show_image = true;
-                        if self.image_converted {
-                            self.screen_state = 2;
+                }
+                // the preview is removed as soon as the cursor leaves the image
+                if self.starting_point.take().is_some() || self.magnify_source.is_some() {
+                    self.screenshot.rollback_changes();
+                    self.conversion();
+                }
+                false
+            })
+        }
+
+        /// Dragging adds a region which stays at full brightness, a secondary click on a region removes it
+        pub fn draw_spotlight(&mut self, ctx: &Context, available: Vec2) -> bool {
+            ctx.input(|is| -> bool {
+                let pos = is.pointer.interact_pos();
+                if let Some(pos) = pos {
+                    let texture_coordinates = self.calculate_texture_coordinates(pos, available, ctx.used_size(), false);
+                    if let Some(texture_coordinates) = texture_coordinates {
+                        let x = texture_coordinates.x;
+                        let y = texture_coordinates.y;
+                        if is.pointer.secondary_clicked() {
+                            if self.screenshot.remove_spotlight_at(x, y) {
+                                self.convert_image();
+                            }
+                        } else if is.pointer.primary_down() {
+                            if self.starting_point.is_none() {
+                                self.starting_point = Some((x, y));
+                                self.screenshot.add_spotlight(Spotlight {
+                                    shape: self.spotlight_shape,
+                                    start: (x, y),
+                                    end: (x, y),
+                                });
+                            } else {
+                                self.screenshot.set_last_spotlight_end((x, y));
+                                self.conversion();
+                            }
+                        } else if self.starting_point.take().is_some() {
+                            self.screenshot.discard_empty_spotlights();
+                            self.convert_image();
                         }
+                        return true;
                     }
-                    2 => {
-                        frame.set_window_pos(self.window_pos);
-                        frame.set_window_size(self.window_size);
-                        let result = self.hotkey_manager.set_active_shortcuts(ActiveShortcuts::ScreenshotDone);
-                        self.manage_errors(result);
-                        self.screen_state = 0;
-                        self.screenshot_taken = false;
-                    }
-                    _ => {}
                 }
+                // a spotlight being dragged outside the image is kept with its last valid size
+                if self.starting_point.take().is_some() {
+                    self.screenshot.discard_empty_spotlights();
+                    self.convert_image();
+                }
+                false
+            })
+        }
+
+        /// Width / height ratio imposed to the crop rectangle, if any
+        pub fn crop_ratio(&self) -> Option<f32> {
+            match self.crop_aspect {
+                AspectRatio::Free => None,
+                AspectRatio::Square => Some(1.0),
+                AspectRatio::FourThree => Some(4.0 / 3.0),
+                AspectRatio::SixteenNine => Some(16.0 / 9.0),
+                AspectRatio::Custom => Some(self.crop_custom_ratio[0].max(0.01) / self.crop_custom_ratio[1].max(0.01)),
             }
         }
 
-        pub fn manage_errors<E>(&mut self, result: Result<E, Box<dyn Error>>) -> Option<E> {
-            match result {
-                Ok(value) => Some(value),
-                Err(e) => {
-                    self.previous_drawing_mode_error = self.drawing_mode;
-                    self.drawing_mode = None;
-                    self.error_message = e.to_string();
-                    self.error_dialog = true;
-                    None
-                }
+        fn image_size(&self) -> Vec2 {
+            Vec2::new(self.screenshot.get_width() as f32, self.screenshot.get_height() as f32)
+        }
+
+        /// Adapts the pending crop rectangle to the selected aspect ratio keeping its top left corner
+        pub fn fit_crop_to_ratio(&mut self) {
+            if let (Some(rect), Some(ratio)) = (self.crop_rect, self.crop_ratio()) {
+                let corner = rect.min + Vec2::new(rect.width(), rect.width() / ratio);
+                self.crop_rect = Some(crop_rect_from_anchor(rect.min, corner, Some(ratio), self.image_size()));
             }
         }
-    }
 
-    impl App for ScreenshotStr {
-        fn update(&mut self, ctx: &Context, frame: &mut eframe::Frame) {
-            //shortcuts
-            if let Ok(event) = GlobalHotKeyEvent::receiver().try_recv() {
-                //KEY_QUICK
-                if self.hotkey_manager.get_key(KeyType::Quick).is_some() && self.hotkey_manager.get_key(KeyType::Quick).unwrap() == event.id {
-                    println!("Screenshot taken");
-                    let startup_settings = read_settings_from_file("settings.json".to_string());
-                    let startup_settings = self.manage_errors(startup_settings);
-                    if startup_settings.is_some() {
-                        let ss = take_screenshot(Duration::from_secs(0), 0);
-                        let result = ss.save_image(&PathBuf::from(startup_settings.unwrap().path), ImageFormat::Png);
-                        self.manage_errors(result);
-                    }
-                }
-                //KEY_SCREENSHOT
-                if self.hotkey_manager.get_key(KeyType::NewScreenshot).is_some() && self.hotkey_manager.get_key(KeyType::NewScreenshot).unwrap() == event.id {
-                    self.window_size = frame.info().window_info.size;
-                    self.window_pos = frame.info().window_info.position.unwrap();
-                    self.screenshot_taken = true;
-                    let result = self.hotkey_manager.set_active_shortcuts(ActiveShortcuts::Pause);
-                    self.manage_errors(result);
-                }
-                //KEY_SAVE
-                if self.hotkey_manager.get_key(KeyType::Save).is_some() && self.hotkey_manager.get_key(KeyType::Save).unwrap() == event.id {
-                    if !self.saved_to_clipboard_dialog && !self.settings_dialog && !self.save_dialog {
-                        if self.drawing_mode == Some(DrawingMode::Crop) || self.drawing_mode == Some(DrawingMode::Shape){
-                            self.screenshot.rollback_changes();
-                            self.conversion();
+        /// Updates the pending crop rectangle from the pointer: pressing on a handle drags that corner
+        /// or side, pressing inside moves the rectangle and pressing outside starts a new one;
+        /// Enter applies the crop and Escape discards the rectangle.
+        /// Returns the cursor to show, None when the pointer is not on the image
+        pub fn draw_crop(&mut self, ctx: &Context, available: Vec2) -> Option<CursorIcon> {
+            let size = self.image_size();
+            let w_scale = self.calculate_rect_image(available, ctx.used_size()).4;
+            let ratio = self.crop_ratio();
+            let (enter_pressed, exit_pressed) = ctx.input(|is| (is.key_pressed(Key::Enter), is.key_pressed(Key::Escape)));
+            if enter_pressed {
+                self.apply_crop();
+                return None;
+            }
+            if exit_pressed {
+                self.crop_rect = None;
+                self.crop_drag = None;
+            }
+            ctx.input(|is| -> Option<CursorIcon> {
+                let pos = is.pointer.interact_pos()?;
+                let inside = self.calculate_texture_coordinates(pos, available, ctx.used_size(), false).is_some();
+                let p = self.calculate_texture_coordinates(pos, available, ctx.used_size(), true)?;
+                let p = Pos2::new(p.x.clamp(0.0, size.x), p.y.clamp(0.0, size.y));
+                // handles are hit within 8 screen pixels
+                let handle = self.crop_rect.and_then(|rect| crop_handle_at(rect, p, 8.0 / w_scale));
+                if is.pointer.primary_pressed() && inside {
+                    self.crop_drag = match (self.crop_rect, handle) {
+                        (Some(rect), Some((hx, hy))) if hx != 0 && hy != 0 => {
+                            let anchor = Pos2::new(if hx < 0 { rect.max.x } else { rect.min.x }, if hy < 0 { rect.max.y } else { rect.min.y });
+                            Some(CropDrag::Resize { anchor })
                         }
-                        self.previous_drawing_mode = self.drawing_mode;
-                        self.drawing_mode = None;
-                    }
-                    self.starting_point=None;
-                    self.saved_to_clipboard_dialog = false;
-                    self.text_edit_dialog = false;
-                    self.settings_dialog = false;
-                    self.save_dialog = true;
+                        (Some(rect), Some((hx, hy))) => Some(CropDrag::Side { start: rect, hx, hy }),
+                        (Some(rect), None) if rect.contains(p) => Some(CropDrag::Move { offset: p - rect.min }),
+                        _ => Some(CropDrag::Resize { anchor: p }),
+                    };
                 }
-                //KEY_PEN
-                if self.hotkey_manager.get_key(KeyType::Pen).is_some() && self.hotkey_manager.get_key(KeyType::Pen).unwrap() == event.id {
-                    if self.drawing_mode == Some(DrawingMode::Crop){
-                        self.screenshot.rollback_changes();
-                        self.conversion();
+                if !is.pointer.primary_down() {
+                    self.crop_drag = None;
+                    if self.crop_rect.is_some_and(|rect| rect.width() < 1.0 || rect.height() < 1.0) {
+                        self.crop_rect = None;
                     }
-                    self.starting_point=None;
-                    self.drawing_mode = Some(DrawingMode::Paint);
                 }
-                //KEY_RUBBER
-                if self.hotkey_manager.get_key(KeyType::Rubber).is_some() && self.hotkey_manager.get_key(KeyType::Rubber).unwrap() == event.id {
-                    if self.drawing_mode == Some(DrawingMode::Crop){
-                        self.screenshot.rollback_changes();
-                        self.conversion();
+                match self.crop_drag {
+                    Some(CropDrag::Resize { anchor }) => {
+                        self.crop_rect = Some(crop_rect_from_anchor(anchor, p, ratio, size));
                     }
-                    self.starting_point=None;
-                    self.drawing_mode = Some(DrawingMode::Erase);
-                }
-            }
-
-            //DIALOGS
-
-            // SAVE_DIALOG
-            if self.save_dialog {
-                Window::new("Save Screenshot")
-                    .collapsible(false)
-                    .resizable(false)
-                    .show(ctx, |ui| {
-                        ui.set_enabled(!self.error_dialog);
-
-                        //close button
-                        ui.horizontal(|ui| {
-                            ui.label("Save as?");
-                            if ui.button("PNG").clicked() {
-                                self.format = ImageFormat::Png;
-                                //error handling
-                                let result = self.screenshot.save_image(&PathBuf::from(&self.settings.path), self.format);
-                                if self.manage_errors(result).is_none() {
-                                    return;
-                                }
-                                self.save_dialog = false;
-                                self.drawing_mode = self.previous_drawing_mode;
+                    Some(CropDrag::Move { offset }) => {
+                        let rect = self.crop_rect?;
+                        let min = Pos2::new((p.x - offset.x).clamp(0.0, size.x - rect.width()), (p.y - offset.y).clamp(0.0, size.y - rect.height()));
+                        self.crop_rect = Some(Rect::from_min_size(min, rect.size()));
+                    }
+                    Some(CropDrag::Side { start, hx, hy }) => {
+                        self.crop_rect = Some(match ratio {
+                            None => {
+                                let (mut min, mut max) = (start.min, start.max);
+                                if hx < 0 { min.x = p.x } else if hx > 0 { max.x = p.x }
+                                if hy < 0 { min.y = p.y } else if hy > 0 { max.y = p.y }
+                                Rect::from_two_pos(min, max)
                             }
-                            if ui.button("JPG").clicked() {
-                                self.format = ImageFormat::Jpeg;
-                                let result = self.screenshot.save_image(&PathBuf::from(&self.settings.path), self.format);
-                                if self.manage_errors(result).is_none() {
-                                    return;
-                                }
-                                self.save_dialog = false;
-                                self.drawing_mode = self.previous_drawing_mode;
+                            // with a locked ratio the other dimension grows from the top or left side
+                            Some(ratio) if hx != 0 => {
+                                let anchor = Pos2::new(if hx < 0 { start.max.x } else { start.min.x }, start.min.y);
+                                let corner = Pos2::new(p.x, anchor.y + (p.x - anchor.x).abs() / ratio);
+                                crop_rect_from_anchor(anchor, corner, Some(ratio), size)
                             }
-                            if ui.button("GIF").clicked() {
-                                self.format = ImageFormat::Gif;
-                                let result = self.screenshot.save_image(&PathBuf::from(&self.settings.path), self.format);
-                                if self.manage_errors(result).is_none() {
-                                    return;
-                                }
-                                self.save_dialog = false;
-                                self.drawing_mode = self.previous_drawing_mode;
+                            Some(ratio) => {
+                                let anchor = Pos2::new(start.min.x, if hy < 0 { start.max.y } else { start.min.y });
+                                let corner = Pos2::new(anchor.x + (p.y - anchor.y).abs() * ratio, p.y);
+                                crop_rect_from_anchor(anchor, corner, Some(ratio), size)
                             }
                         });
+                    }
+                    None => {}
+                }
+                if !inside && self.crop_drag.is_none() {
+                    return None;
+                }
+                Some(match (self.crop_drag, handle) {
+                    (Some(CropDrag::Move { .. }), _) => CursorIcon::Grabbing,
+                    (None, Some((hx, hy))) if hx * hy > 0 => CursorIcon::ResizeNwSe,
+                    (None, Some((hx, hy))) if hx * hy < 0 => CursorIcon::ResizeNeSw,
+                    (None, Some((0, _))) => CursorIcon::ResizeVertical,
+                    (None, Some(_)) => CursorIcon::ResizeHorizontal,
+                    (None, None) if self.crop_rect.is_some_and(|rect| rect.contains(p)) => CursorIcon::Grab,
+                    _ => CursorIcon::Crosshair,
+                })
+            })
+        }
 
-                        //close
-                        ui.horizontal(|ui| {
-                            if ui.button("Cancel").clicked() {
-                                self.drawing_mode = self.previous_drawing_mode;
-                                self.save_dialog = false;
-                            }
-                        });
-                    });
+        /// Shades the area which is going to be cut away and draws the border, the handles and (if
+        /// enabled) the rule of thirds grid of the pending crop rectangle
+        pub fn paint_crop_overlay(&self, painter: &egui::Painter, available: Vec2, total_window: Vec2) {
+            let rect = match self.crop_rect {
+                Some(rect) => rect,
+                None => return,
+            };
+            let (image_pos_x, image_pos_y, width, height, w_scale, h_scale) = self.calculate_rect_image(available, total_window);
+            let to_screen = |p: Pos2| Pos2::new(image_pos_x + p.x * w_scale, image_pos_y + p.y * h_scale);
+            let image = Rect::from_min_size(Pos2::new(image_pos_x, image_pos_y), Vec2::new(width, height));
+            let crop = Rect::from_min_max(to_screen(rect.min), to_screen(rect.max));
+            let shade = Color32::from_black_alpha(140);
+            painter.rect_filled(Rect::from_min_max(image.min, Pos2::new(image.max.x, crop.min.y)), 0.0, shade);
+            painter.rect_filled(Rect::from_min_max(Pos2::new(image.min.x, crop.max.y), image.max), 0.0, shade);
+            painter.rect_filled(Rect::from_min_max(Pos2::new(image.min.x, crop.min.y), Pos2::new(crop.min.x, crop.max.y)), 0.0, shade);
+            painter.rect_filled(Rect::from_min_max(Pos2::new(crop.max.x, crop.min.y), Pos2::new(image.max.x, crop.max.y)), 0.0, shade);
+            if self.crop_thirds {
+                let stroke = Stroke::new(1.0, Color32::from_white_alpha(120));
+                for i in 1..=2 {
+                    let x = crop.min.x + crop.width() * i as f32 / 3.0;
+                    let y = crop.min.y + crop.height() * i as f32 / 3.0;
+                    painter.line_segment([Pos2::new(x, crop.min.y), Pos2::new(x, crop.max.y)], stroke);
+                    painter.line_segment([Pos2::new(crop.min.x, y), Pos2::new(crop.max.x, y)], stroke);
+                }
             }
+            painter.rect_stroke(crop, 0.0, Stroke::new(1.0, Color32::WHITE));
+            for (hx, hy) in CROP_HANDLES {
+                painter.rect_filled(Rect::from_center_size(crop_handle_pos(crop, hx, hy), Vec2::splat(7.0)), 0.0, Color32::WHITE);
+            }
+        }
 
+        /// Crops the image to the pending rectangle, restoring the previous image on failure
+        pub fn apply_crop(&mut self) {
+            if let Some(rect) = self.crop_rect.take() {
+                let size = self.image_size();
+                let (x0, y0) = (rect.min.x.round().max(0.0) as u32, rect.min.y.round().max(0.0) as u32);
+                let (x1, y1) = (rect.max.x.round().min(size.x) as u32, rect.max.y.round().min(size.y) as u32);
+                self.push_undo(self.crop_screenshot_tmp.clone());
+                let result = self.screenshot.resize_image(x0, y0, y1 as i32 - y0 as i32, x1 as i32 - x0 as i32);
+                if self.manage_errors(result).is_none() {
+                    self.screenshot = self.crop_screenshot_tmp.clone();
+                }
+                self.image_transformed();
+            }
+            self.crop_drag = None;
+        }
 
-            if self.saved_to_clipboard_dialog {
-                Window::new("Save Screenshot")
-                    .collapsible(false)
-                    .title_bar(false)
-                    .resizable(false)
-                    .movable(false)
-                    .show(ctx, |ui| {
-                        ui.set_enabled(!self.error_dialog);
+        /// Shows a new image in the editor, forgetting everything about the previous one
+        fn open_screenshot(&mut self, screenshot: Screenshot, title: String) {
+            // the image already open stays in its tab
+            if self.show_image {
+                self.apply_overlay();
+                let current = self.take_document();
+                self.tabs.insert(self.active_tab, current);
+                self.active_tab = self.tabs.len();
+            }
+            let document = Document::new(screenshot, title, self.format, PathBuf::from(self.save_folder()));
+            self.put_document(document);
+        }
 
-                        if ui.label("Saved to clipboard!").clicked_elsewhere() {
-                            self.saved_to_clipboard_dialog = false;
-                        };
-                        if ui.button("Ok").clicked() {
-                            self.saved_to_clipboard_dialog = false;
-                            self.drawing_mode = self.previous_drawing_mode;
-                        }
-                    });
+        /// Moves the image of the selected tab and everything about it out of the editor
+        fn take_document(&mut self) -> Document {
+            self.starting_point = None;
+            self.text_edit_dialog = false;
+            Document {
+                title: std::mem::take(&mut self.title),
+                screenshot: std::mem::replace(&mut self.screenshot, Screenshot::new_empty()),
+                crop_screenshot_tmp: std::mem::replace(&mut self.crop_screenshot_tmp, Screenshot::new_empty()),
+                undo_stack: std::mem::take(&mut self.undo_stack),
+                redo_stack: std::mem::take(&mut self.redo_stack),
+                zoom: std::mem::replace(&mut self.zoom, 1.0),
+                pan: std::mem::replace(&mut self.pan, Vec2::ZERO),
+                drawing_mode: self.drawing_mode.take(),
+                crop_rect: self.crop_rect.take(),
+                crop_drag: self.crop_drag.take(),
+                magnify_source: self.magnify_source.take(),
+                overlay: self.overlay.take(),
+                last_saved: self.last_saved.take(),
+                target_format: self.target_format,
+                target_dir: std::mem::take(&mut self.target_dir),
+                caption: std::mem::take(&mut self.caption),
+                dirty: std::mem::replace(&mut self.dirty, false),
+            }
+        }
+
+        /// Shows the document in the editor, as it was when it was taken out
+        fn put_document(&mut self, document: Document) {
+            self.title = document.title;
+            self.screenshot = document.screenshot;
+            self.crop_screenshot_tmp = document.crop_screenshot_tmp;
+            self.undo_stack = document.undo_stack;
+            self.redo_stack = document.redo_stack;
+            self.zoom = document.zoom;
+            self.pan = document.pan;
+            self.drawing_mode = document.drawing_mode;
+            self.crop_rect = document.crop_rect;
+            self.crop_drag = document.crop_drag;
+            self.magnify_source = document.magnify_source;
+            self.overlay = document.overlay;
+            self.last_saved = document.last_saved;
+            self.target_format = document.target_format;
+            self.target_dir = document.target_dir;
+            self.caption = document.caption;
+            self.dirty = document.dirty;
+            self.starting_point = None;
+            self.convert_image();
+            self.show_image = true;
+        }
+
+        fn tab_count(&self) -> usize {
+            if self.show_image { self.tabs.len() + 1 } else { 0 }
+        }
+
+        /// Title of a tab and whether it has unsaved changes
+        fn tab_title(&self, index: usize) -> (&str, bool) {
+            match index.cmp(&self.active_tab) {
+                std::cmp::Ordering::Equal => (&self.title, self.dirty),
+                std::cmp::Ordering::Less => (&self.tabs[index].title, self.tabs[index].dirty),
+                std::cmp::Ordering::Greater => (&self.tabs[index - 1].title, self.tabs[index - 1].dirty),
+            }
+        }
+
+        fn switch_tab(&mut self, index: usize) {
+            if index == self.active_tab || index >= self.tab_count() {
+                return;
+            }
+            self.apply_overlay();
+            let current = self.take_document();
+            self.tabs.insert(self.active_tab, current);
+            let document = self.tabs.remove(index);
+            self.active_tab = index;
+            self.put_document(document);
+        }
+
+        /// Closes the tab, asking first what to do with its unsaved changes
+        fn request_close_tab(&mut self, index: usize) {
+            self.switch_tab(index);
+            if self.dirty {
+                self.close_prompt = Some(ClosePrompt::Tab);
+            } else {
+                self.close_tab();
+            }
+        }
+
+        /// Closes the selected tab, the next one is selected
+        fn close_tab(&mut self) {
+            self.take_document();
+            self.schedule_autosave(true);
+            if self.tabs.is_empty() {
+                self.show_image = false;
+                self.active_tab = 0;
+                let result = self.hotkey_manager.set_context(ShortcutContext::ScreenshotWaiting);
+                self.manage_errors(result);
+            } else {
+                self.active_tab = self.active_tab.min(self.tabs.len() - 1);
+                let document = self.tabs.remove(self.active_tab);
+                self.put_document(document);
+            }
+        }
+
+        /// Asks for an autosave once the interval of the settings is over, or straight away after
+        /// a capture was saved or closed so that it leaves the recovery directory
+        fn schedule_autosave(&mut self, now: bool) {
+            let delay = if now { Duration::ZERO } else { Duration::from_secs(self.settings.autosave_interval) };
+            let due = Instant::now() + delay;
+            self.autosave_due = Some(self.autosave_due.map_or(due, |scheduled| scheduled.min(due)));
+        }
+
+        /// Writes the captures with unsaved changes to the recovery directory when it is time,
+        /// in the background, so that they can be restored if the app does not close properly
+        fn autosave(&mut self) {
+            let Some(dir) = self.recovery.as_ref().map(|session| session.dir.clone()) else {
+                return;
+            };
+            if self.settings.autosave_interval == 0 {
+                return;
+            }
+            let due = self.autosave_due.is_some_and(|due| due <= Instant::now());
+            let writing = self.autosave_thread.as_ref().is_some_and(|thread| !thread.is_finished());
+            if !due || writing {
+                return;
+            }
+            self.autosave_due = None;
+            let documents = self.recovery_documents();
+            self.autosave_thread = Some(std::thread::spawn(move || {
+                match write_recovery(&dir, &documents) {
+                    Ok(()) => log::debug!("Autosaved {} captures", documents.len()),
+                    Err(e) => log::error!("Autosave failed: {}", e),
+                }
+            }));
+        }
+
+        /// The captures with unsaved changes, with the last edits which can be undone
+        fn recovery_documents(&self) -> Vec<RecoveryDocument> {
+            let recent = |undo_stack: &[Screenshot]| undo_stack[undo_stack.len().saturating_sub(RECOVERED_UNDO_STEPS)..].to_vec();
+            let mut documents = Vec::new();
+            if self.show_image && self.dirty {
+                documents.push(RecoveryDocument {
+                    title: self.title.clone(),
+                    caption: self.caption.clone(),
+                    screenshot: self.screenshot.clone(),
+                    undo_stack: recent(&self.undo_stack),
+                });
+            }
+            for document in self.tabs.iter().filter(|document| document.dirty) {
+                documents.push(RecoveryDocument {
+                    title: document.title.clone(),
+                    caption: document.caption.clone(),
+                    screenshot: document.screenshot.clone(),
+                    undo_stack: recent(&document.undo_stack),
+                });
+            }
+            documents
+        }
+
+        /// Opens the captures left by the sessions which did not close properly as unsaved tabs,
+        /// as they were and with their last edits; their directories are deleted once the
+        /// captures are written to the directory of this session
+        fn restore_recovered(&mut self) {
+            let sessions = std::mem::take(&mut self.recovered);
+            for entry in sessions.iter().flat_map(|session| &session.entries) {
+                let result = entry.snapshot.open();
+                if let Some(screenshot) = self.manage_errors(result) {
+                    self.open_in_editor(screenshot, entry.title.clone());
+                    // an edit whose images cannot be read anymore cannot be undone
+                    self.undo_stack = entry.undo_stack.iter().filter_map(|snapshot| snapshot.open().ok()).collect();
+                    self.caption = entry.caption.clone();
+                    self.dirty = true;
+                }
+            }
+            if let Some(thread) = self.autosave_thread.take() {
+                let _ = thread.join();
+            }
+            if let Some(session) = &self.recovery {
+                let result = write_recovery(&session.dir, &self.recovery_documents());
+                if self.manage_errors(result).is_none() {
+                    // kept to be offered again by the next start
+                    return;
+                }
+            }
+            for session in sessions {
+                let result = session.discard();
+                self.manage_errors(result);
+            }
+        }
+
+        /// Deletes the captures left by the sessions which did not close properly
+        fn discard_recovered(&mut self) {
+            for session in std::mem::take(&mut self.recovered) {
+                let result = session.discard();
+                self.manage_errors(result);
+            }
+        }
+
+        fn unsaved_count(&self) -> usize {
+            self.tabs.iter().filter(|document| document.dirty).count() + usize::from(self.show_image && self.dirty)
+        }
+
+        /// Folder the save dialog starts from: the last one used, or the one of the settings
+        fn save_folder(&self) -> String {
+            if self.editor_state.last_save_dir.is_empty() {
+                self.settings.get_path()
+            } else {
+                self.editor_state.last_save_dir.clone()
+            }
+        }
+
+        /// Saves the captures with unsaved changes with their own format and folder, the selected
+        /// one only or all of them; returns whether everything was saved
+        fn save_unsaved(&mut self, all: bool) -> bool {
+            self.schedule_autosave(true);
+            if self.dirty {
+                self.apply_overlay();
+                let metadata = save_metadata(&self.settings, &self.screenshot, &self.caption);
+                let result = self.screenshot.save_image(&self.target_dir, self.target_format, metadata.as_ref());
+                match self.manage_errors(result) {
+                    Some(saved) => {
+                        self.last_saved = Some(saved);
+                        self.dirty = false;
+                    }
+                    None => return false,
+                }
+            }
+            if all {
+                for index in 0..self.tabs.len() {
+                    let document = &self.tabs[index];
+                    if document.dirty {
+                        let metadata = save_metadata(&self.settings, &document.screenshot, &document.caption);
+                        let result = document.screenshot.save_image(&document.target_dir, document.target_format, metadata.as_ref());
+                        match self.manage_errors(result) {
+                            Some(saved) => {
+                                self.tabs[index].last_saved = Some(saved);
+                                self.tabs[index].dirty = false;
+                            }
+                            None => return false,
+                        }
+                    }
+                }
+            }
+            true
+        }
+
+        fn add_to_history(&mut self, screenshot: &Screenshot, source: CaptureSource) {
+            let result = self.history.add(screenshot, source, self.settings.history_limit);
+            self.manage_errors(result);
+        }
+
+        /// Lists the screenshots of the default save directory and of the last one used
+        fn refresh_saved_screenshots(&mut self) {
+            let mut dirs = vec![self.settings.get_path()];
+            if !self.editor_state.last_save_dir.is_empty() && !dirs.contains(&self.editor_state.last_save_dir) {
+                dirs.push(self.editor_state.last_save_dir.clone());
+            }
+            let mut saved: Vec<HistoryEntry> = dirs.iter().flat_map(|dir| saved_screenshots(Path::new(dir))).collect();
+            saved.sort_by_key(|entry| std::cmp::Reverse(entry.taken_at));
+            saved.truncate(self.settings.history_limit);
+            self.history_saved = saved;
+        }
+
+        /// Thumbnail, details and buttons of a capture of the history panel; thumbnails are
+        /// loaded one per frame so that opening the panel does not freeze the window
+        fn history_entry_ui(&mut self, ui: &mut egui::Ui, entry: &HistoryEntry, thumbnail_loaded: &mut bool) -> Option<HistoryCommand> {
+            if !self.history_thumbnails.contains_key(&entry.file) && !*thumbnail_loaded {
+                *thumbnail_loaded = true;
+                if let Ok(thumbnail) = entry.thumbnail(192) {
+                    let pixels = ColorImage::from_rgba_unmultiplied([thumbnail.width() as usize, thumbnail.height() as usize], thumbnail.to_rgba8().as_bytes());
+                    let texture = ui.ctx().load_texture(entry.file.to_string_lossy(), pixels, Default::default());
+                    self.history_thumbnails.insert(entry.file.clone(), texture);
+                }
+            }
+            let mut command = None;
+            ui.group(|ui| {
+                match self.history_thumbnails.get(&entry.file) {
+                    Some(texture) => {
+                        let size = texture.size_vec2() * (192.0 / texture.size_vec2().max_elem()).min(1.0);
+                        ui.image(texture.id(), size);
+                    }
+                    None => {
+                        ui.spinner();
+                    }
+                }
+                ui.label(format!("{}  {}x{}  {}", entry.taken_at(), entry.width, entry.height, entry.source));
+                ui.horizontal(|ui| {
+                    if ui.button("Open").clicked() {
+                        command = Some(HistoryCommand::Open);
+                    }
+                    if ui.button("\u{1F4CB}").on_hover_text("Copy to clipboard").clicked() {
+                        command = Some(HistoryCommand::Copy);
+                    }
+                    ui.menu_button("Save as", |ui| {
+                        for (format, name) in [(ImageFormat::Png, "PNG"), (ImageFormat::Jpeg, "JPG"), (ImageFormat::Gif, "GIF")] {
+                            if ui.button(name).clicked() {
+                                ui.close_menu();
+                                command = Some(HistoryCommand::SaveAs(format));
+                            }
+                        }
+                    });
+                    // deleting takes a second click
+                    let confirming = self.history_confirm_delete.as_ref() == Some(&entry.file);
+                    if ui.button(if confirming { "Confirm" } else { "Delete" }).clicked() {
+                        if confirming {
+                            command = Some(HistoryCommand::Delete);
+                        } else {
+                            self.history_confirm_delete = Some(entry.file.clone());
+                        }
+                    }
+                });
+            });
+            command
+        }
+
+        /// Runs a command of the history panel on the capture, index tells where it is in the
+        /// history, None for the screenshots of the save directory
+        fn run_history_command(&mut self, entry: &HistoryEntry, index: Option<usize>, command: HistoryCommand) {
+            match command {
+                HistoryCommand::Open => {
+                    if let Some(screenshot) = self.manage_errors(entry.open()) {
+                        self.open_in_editor(screenshot, file_title(&entry.file));
+                    }
+                }
+                HistoryCommand::Copy => {
+                    let result = entry.open().and_then(|screenshot| screenshot.save_to_clipboard(self.settings.clipboard_mode, Some(&entry.file)));
+                    if self.manage_errors(result).is_some() {
+                        if !self.any_dialog_open() {
+                            self.previous_drawing_mode = self.drawing_mode;
+                            self.drawing_mode = None;
+                        }
+                        self.saved_to_clipboard_dialog = true;
+                    }
+                }
+                HistoryCommand::SaveAs(format) => {
+                    let result = entry.open().and_then(|screenshot| {
+                        let metadata = save_metadata(&self.settings, &screenshot, &Caption::default());
+                        screenshot.save_image(&PathBuf::from(self.save_folder()), format, metadata.as_ref())
+                    });
+                    if self.manage_errors(result).is_some() {
+                        self.refresh_saved_screenshots();
+                    }
+                }
+                HistoryCommand::Delete => {
+                    self.history_confirm_delete = None;
+                    self.history_thumbnails.remove(&entry.file);
+                    let result = match index {
+                        Some(index) => self.history.remove(index),
+                        None => std::fs::remove_file(&entry.file).with_path(&entry.file),
+                    };
+                    self.manage_errors(result);
+                    self.refresh_saved_screenshots();
+                }
+            }
+        }
+
+        /// Opens an image which was not captured, like a pasted or dropped one
+        fn open_in_editor(&mut self, screenshot: Screenshot, title: String) {
+            self.open_screenshot(screenshot, title);
+            self.drawing_mode = None;
+            let result = self.hotkey_manager.set_context(ShortcutContext::ScreenshotDone);
+            self.manage_errors(result);
+        }
+
+        /// Moves and resizes the pasted image from the pointer like the crop rectangle, the corners
+        /// keep its proportions unless Shift is held; Enter applies it and Escape discards it.
+        /// Returns the cursor to show, None when the pointer is not on the image
+        pub fn draw_overlay(&mut self, ctx: &Context, available: Vec2) -> Option<CursorIcon> {
+            let w_scale = self.calculate_rect_image(available, ctx.used_size()).4;
+            let (enter_pressed, exit_pressed) = ctx.input(|is| (is.key_pressed(Key::Enter), is.key_pressed(Key::Escape)));
+            if enter_pressed {
+                self.apply_overlay();
+                return None;
+            }
+            if exit_pressed {
+                self.overlay = None;
+                self.drawing_mode = None;
+                return None;
+            }
+            ctx.input(|is| -> Option<CursorIcon> {
+                let pos = is.pointer.interact_pos()?;
+                let inside = self.calculate_texture_coordinates(pos, available, ctx.used_size(), false).is_some();
+                let p = self.calculate_texture_coordinates(pos, available, ctx.used_size(), true)?;
+                let overlay = self.overlay.as_mut()?;
+                let ratio = (!is.modifiers.shift).then(|| overlay.image.width() as f32 / overlay.image.height() as f32);
+                // handles are hit within 8 screen pixels
+                let handle = crop_handle_at(overlay.rect, p, 8.0 / w_scale);
+                let rect = overlay.rect;
+                if is.pointer.primary_pressed() && inside {
+                    overlay.drag = match handle {
+                        Some((hx, hy)) if hx != 0 && hy != 0 => {
+                            let anchor = Pos2::new(if hx < 0 { rect.max.x } else { rect.min.x }, if hy < 0 { rect.max.y } else { rect.min.y });
+                            Some(CropDrag::Resize { anchor })
+                        }
+                        Some((hx, hy)) => Some(CropDrag::Side { start: rect, hx, hy }),
+                        None if rect.contains(p) => Some(CropDrag::Move { offset: p - rect.min }),
+                        None => None,
+                    };
+                }
+                if !is.pointer.primary_down() {
+                    overlay.drag = None;
+                }
+                match overlay.drag {
+                    // the pasted image may go past the right and bottom sides of the capture
+                    Some(CropDrag::Resize { anchor }) => {
+                        overlay.rect = crop_rect_from_anchor(anchor, p, ratio, Vec2::splat(f32::INFINITY));
+                    }
+                    Some(CropDrag::Move { offset }) => overlay.rect = Rect::from_min_size(p - offset, rect.size()),
+                    Some(CropDrag::Side { start, hx, hy }) => {
+                        let (mut min, mut max) = (start.min, start.max);
+                        if hx < 0 { min.x = p.x } else if hx > 0 { max.x = p.x }
+                        if hy < 0 { min.y = p.y } else if hy > 0 { max.y = p.y }
+                        overlay.rect = Rect::from_two_pos(min, max);
+                    }
+                    None => {}
+                }
+                if !inside && overlay.drag.is_none() {
+                    return None;
+                }
+                Some(match (overlay.drag, handle) {
+                    (Some(CropDrag::Move { .. }), _) => CursorIcon::Grabbing,
+                    (None, Some((hx, hy))) if hx * hy > 0 => CursorIcon::ResizeNwSe,
+                    (None, Some((hx, hy))) if hx * hy < 0 => CursorIcon::ResizeNeSw,
+                    (None, Some((0, _))) => CursorIcon::ResizeVertical,
+                    (None, Some(_)) => CursorIcon::ResizeHorizontal,
+                    (None, None) if overlay.rect.contains(p) => CursorIcon::Grab,
+                    _ => CursorIcon::Default,
+                })
+            })
+        }
+
+        /// Shows the pasted image where it is going to be drawn, with its border and handles
+        pub fn paint_overlay(&mut self, ctx: &Context, painter: &egui::Painter, available: Vec2) {
+            let (image_pos_x, image_pos_y, _, _, w_scale, h_scale) = self.calculate_rect_image(available, ctx.used_size());
+            let overlay = match self.overlay.as_mut() {
+                Some(overlay) => overlay,
+                None => return,
+            };
+            let to_screen = |p: Pos2| Pos2::new(image_pos_x + p.x * w_scale, image_pos_y + p.y * h_scale);
+            let rect = Rect::from_min_max(to_screen(overlay.rect.min), to_screen(overlay.rect.max));
+            let image = &overlay.image;
+            let texture = overlay.texture.get_or_insert_with(|| {
+                let pixels = ColorImage::from_rgba_unmultiplied([image.width() as usize, image.height() as usize], image.to_rgba8().as_bytes());
+                ctx.load_texture("overlay", pixels, Default::default())
+            });
+            let uv = Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0));
+            painter.image(texture.id(), rect, uv, Color32::WHITE);
+            painter.rect_stroke(rect, 0.0, Stroke::new(1.0, Color32::WHITE));
+            for (hx, hy) in CROP_HANDLES {
+                painter.rect_filled(Rect::from_center_size(crop_handle_pos(rect, hx, hy), Vec2::splat(7.0)), 0.0, Color32::WHITE);
+            }
+        }
+
+        /// Draws the pasted image into the capture where it was placed
+        pub fn apply_overlay(&mut self) {
+            if let Some(overlay) = self.overlay.take() {
+                let rect = overlay.rect;
+                if rect.width() >= 1.0 && rect.height() >= 1.0 {
+                    self.push_undo(self.screenshot.clone());
+                    let result = self.screenshot.overlay_image(&overlay.image, rect.min.x.round() as i64, rect.min.y.round() as i64, rect.width().round() as u32, rect.height().round() as u32);
+                    self.manage_errors(result);
+                    self.image_transformed();
+                }
+            }
+            if self.drawing_mode == Some(DrawingMode::Overlay) {
+                self.drawing_mode = None;
+            }
+        }
+
+        /// Called after the whole image has been transformed, the crop backup has to follow the new image
+        fn image_transformed(&mut self) {
+            self.crop_screenshot_tmp = self.screenshot.clone();
+            self.convert_image();
+            self.show_image = true;
+        }
+
+        fn conversion(&mut self) {
+            if Instant::now() > self.instant {
+                self.convert_image();
+                self.instant += Duration::from_millis(5);
+            }
+        }
+
+        pub fn check_minimization(&mut self, frame: &mut eframe::Frame) {
+            if self.screenshot_taken {
+                match self.screen_state {
+                    0 => {
+                        frame.set_window_pos(Pos2::new(-200.0, -200.0));
+                        frame.set_window_size(Vec2::new(0.0, 0.0));
+                        // the position is unknown on some platforms, like Wayland
+                        if frame.info().window_info.position.is_none_or(|pos| pos.x == -200.0 && pos.y == -200.0) {
+                            self.screen_state = 1;
+                        }
+                    }
+                    1 => {
+                        let preset = self.running_preset.take();
+                        let (delay, screen) = preset.as_ref().map_or((self.timer, self.screen), |preset| (preset.delay, preset.screen));
+                        let result = take_screenshot(Duration::from_secs(delay as u64), screen);
+                        let Some(mut screenshot) = self.manage_errors(result) else {
+                            self.screen_state = 2;
+                            return;
+                        };
+                        if let Some(preset) = &preset {
+                            let result = apply_preset(&mut screenshot, preset, &self.settings);
+                            self.manage_errors(result);
+                        }
+                        self.add_to_history(&screenshot, CaptureSource::Screen(screen));
+                        if let Some(preset) = preset {
+                            if !preset.open_in_editor {
+                                self.screen_state = 2;
+                                return;
+                            }
+                        } else if self.settings.auto_copy && !self.settings.clipboard_mode.needs_file() {
+                            // the modes referencing a file wait for the screenshot to be saved
+                            let result = screenshot.save_to_clipboard(self.settings.clipboard_mode, None);
+                            self.manage_errors(result);
+                        }
+                        self.open_screenshot(screenshot, format!("Capture {}", Local::now().format("%H:%M:%S")));
+                        if self.image_converted {
+                            self.screen_state = 2;
+                        }
+                    }
+                    2 => {
+                        frame.set_window_pos(self.window_pos);
+                        frame.set_window_size(self.window_size);
+                        // a preset may have captured without opening the editor
+                        let context = if self.show_image { ShortcutContext::ScreenshotDone } else { ShortcutContext::ScreenshotWaiting };
+                        let result = self.hotkey_manager.set_context(context);
+                        self.manage_errors(result);
+                        self.screen_state = 0;
+                        self.screenshot_taken = false;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        /// Shows the hotkey bound to the action, if any, when hovering its button
+        fn shortcut_hint(&self, response: Response, action: Action) -> Response {
+            match self.hotkey_manager.get_accelerator(action) {
+                Some(accelerator) => response.on_hover_text(format!("{} ({})", action, accelerator)),
+                None => response.on_hover_text(action.to_string()),
+            }
+        }
+
+        fn any_dialog_open(&self) -> bool {
+            self.saved_to_clipboard_dialog || self.settings_dialog || self.save_dialog || self.adjust_dialog || self.open_dialog
+        }
+
+        /// Leaves the current tool, discarding its preview, and selects the given one
+        fn select_tool(&mut self, mode: DrawingMode) {
+            if self.drawing_mode == Some(DrawingMode::Shape) || self.drawing_mode == Some(DrawingMode::Magnify) {
+                self.screenshot.rollback_changes();
+                self.conversion();
+            }
+            self.starting_point = None;
+            self.text_edit_dialog = false;
+            if mode == DrawingMode::Crop || mode == DrawingMode::Shape || mode == DrawingMode::Magnify {
+                let result = self.screenshot.save_intermediate_image();
+                if self.manage_errors(result).is_none() {
+                    return;
+                }
+            }
+            match mode {
+                DrawingMode::Crop => {
+                    self.crop_screenshot_tmp = self.screenshot.clone();
+                    self.crop_rect = None;
+                    self.crop_drag = None;
+                }
+                DrawingMode::Magnify => self.magnify_source = None,
+                _ => {}
+            }
+            self.toggle_drawing_mode(mode);
+        }
+
+        /// Listens for the key combination of the action: global hotkeys are paused meanwhile,
+        /// otherwise pressing one of them would trigger its action instead of being recorded
+        fn start_hotkey_recording(&mut self, action: Action) {
+            if self.recording_hotkey.is_none() {
+                self.hotkey_context = self.hotkey_manager.get_context();
+            }
+            self.recording_hotkey = Some(action);
+            let result = self.hotkey_manager.set_context(ShortcutContext::Pause);
+            self.manage_errors(result);
+        }
+
+        fn stop_hotkey_recording(&mut self) {
+            if self.recording_hotkey.take().is_some() {
+                let result = self.hotkey_manager.set_context(self.hotkey_context);
+                self.manage_errors(result);
+            }
+        }
+
+        /// Updates the errors shown next to the fields of the settings: the ones found by the
+        /// validation first, then the global hotkeys refused by the OS. Trying the hotkeys out
+        /// registers them for a moment, so it is only done when check_os is set, otherwise the
+        /// last answer is kept for the combinations which did not change
+        fn validate_settings(&mut self, check_os: bool) {
+            let mut errors = self.settings.validate();
+            if check_os {
+                let global: Vec<(Action, String)> = self.settings.bound_hotkeys()
+                    .filter(|(action, _)| action.is_global() && !errors.contains_key(&SettingsField::Hotkey(*action)))
+                    .map(|(action, hotkey)| (action, hotkey.clone()))
+                    .collect();
+                self.unavailable_hotkeys.clear();
+                for (action, hotkey) in global {
+                    if let Ok(accelerator) = Accelerator::from_str(&hotkey) {
+                        if let Err(e) = self.hotkey_manager.check_available(accelerator) {
+                            self.unavailable_hotkeys.insert(action, (hotkey, e.to_string()));
+                        }
+                    }
+                }
+            }
+            for (action, (hotkey, error)) in &self.unavailable_hotkeys {
+                let field = SettingsField::Hotkey(*action);
+                if !errors.contains_key(&field) && self.settings.bound_hotkeys().any(|(bound, current)| bound == *action && current == hotkey) {
+                    errors.insert(field, error.clone());
+                }
+            }
+            self.settings_errors = errors;
+        }
+
+        /// Text field, recorder, reset button and error of the hotkey of an action in the settings,
+        /// returns whether the hotkey changed
+        fn hotkey_row(&mut self, ui: &mut egui::Ui, action: Action) -> bool {
+            let field = ui.add(TextEdit::singleline(self.settings.hotkey_mut(action))
+                .hint_text("Ctrl+Shift+F4"));
+            let mut changed = field.changed();
+            if field.lost_focus() {
+                self.validate_settings(true);
+            }
+            let recording = self.recording_hotkey == Some(action);
+            if ui.selectable_label(recording, "Record").clicked() {
+                if recording {
+                    self.stop_hotkey_recording();
+                } else {
+                    self.start_hotkey_recording(action);
+                }
+            }
+            if ui.button("Default").clicked() {
+                *self.settings.hotkey_mut(action) = Settings::default_hotkey(action);
+                changed = true;
+            }
+            if recording {
+                ui.label("Press a key combination, Esc to cancel, Backspace to unbind");
+            } else if let Some(error) = self.settings_errors.get(&SettingsField::Hotkey(action)) {
+                ui.colored_label(Color32::RED, error);
+            } else {
+                ui.label("");
+            }
+            changed
+        }
+
+        /// Hides the window and captures the screen as soon as it is gone, using the options of
+        /// the preset if any
+        fn start_capture(&mut self, frame: &mut eframe::Frame, preset: Option<CapturePreset>) {
+            let count = get_screens().len();
+            if count == 0 {
+                self.manage_errors::<()>(Err(CaptureError::NoScreens.into()));
+                return;
+            }
+            // the region of a preset is meant for its own screen, so another one is not used instead
+            if let Some(preset) = preset.as_ref().filter(|preset| preset.screen >= count) {
+                self.manage_errors::<()>(Err(CaptureError::MissingScreen { screen: preset.screen, count }.into()));
+                return;
+            }
+            self.window_size = frame.info().window_info.size;
+            if let Some(pos) = frame.info().window_info.position {
+                self.window_pos = pos;
+            }
+            self.screenshot_taken = true;
+            self.running_preset = preset;
+            let result = self.hotkey_manager.set_context(ShortcutContext::Pause);
+            self.manage_errors(result);
+        }
+
+        /// Keeps the editor state up to date with the values used in the window, loading the style
+        /// of a tool when it gets selected, and saves it once it has not changed for a second
+        fn sync_editor_state(&mut self, frame: &eframe::Frame) {
+            let mode = match self.drawing_mode {
+                Some(DrawingMode::Pause) => self.previous_drawing_mode,
+                mode => mode,
+            };
+            if let Some(style) = tool_style(&mut self.editor_state, self.styled_mode) {
+                style.color = self.tool_color;
+                style.size = self.tool_size;
+            }
+            if mode != self.styled_mode {
+                if let Some(style) = tool_style(&mut self.editor_state, mode) {
+                    self.tool_color = style.color;
+                    self.tool_size = style.size;
+                }
+                self.styled_mode = mode;
+            }
+            if let Some(shape) = self.shape {
+                self.editor_state.shape = shape;
+            }
+            self.editor_state.text_font = self.text_font;
+            self.editor_state.marker_shape = self.marker_shape;
+            self.editor_state.marker_size = self.marker_size;
+            self.editor_state.marker_leader = self.marker_leader;
+            self.editor_state.timer = self.timer;
+            self.editor_state.screen = self.screen;
+            self.editor_state.format = self.format.extensions_str()[0].to_string();
+            // the window is moved away while a screenshot is taken
+            if !self.screenshot_taken {
+                let window = &frame.info().window_info;
+                self.editor_state.window_size = Some([window.size.x, window.size.y]);
+                self.editor_state.window_pos = window.position.map(|pos| [pos.x, pos.y]);
+            }
+
+            if self.editor_state == self.saved_editor_state {
+                self.editor_changed_at = None;
+            } else if self.editor_changed_at.get_or_insert_with(Instant::now).elapsed() > Duration::from_secs(1) {
+                self.save_editor_state();
+            }
+        }
+
+        fn save_editor_state(&mut self) {
+            self.editor_changed_at = None;
+            self.saved_editor_state = self.editor_state.clone();
+            // saved in the background while working, so a failure is only logged
+            if let Err(e) = write_editor_state(&self.settings_path, &self.editor_state) {
+                log::warn!("Could not save the editor state: {}", e);
+            }
+            self.settings_modified = settings_modified(&self.settings_path);
+        }
+
+        /// Starts using the given editor state, the style of the selected tool is loaded again
+        fn apply_editor_state(&mut self, editor: EditorState) {
+            self.timer = editor.timer;
+            self.format = ImageFormat::from_extension(&editor.format).unwrap_or(self.format);
+            self.shape = Some(editor.shape);
+            self.editor_state = editor;
+            self.styled_mode = None;
+        }
+
+        /// Applies the settings file when it was changed by someone else, as told by a watcher
+        /// thread which wakes the window up; while the settings window is open the reload waits
+        /// for it to be closed, so the settings being edited are not replaced
+        fn reload_settings(&mut self, ctx: &Context) {
+            let watcher = self.settings_watcher.get_or_insert_with(|| {
+                let ctx = ctx.clone();
+                watch_settings(self.settings_path.clone(), self.settings_modified, move || ctx.request_repaint())
+            });
+            while let Ok(modified) = watcher.try_recv() {
+                // the app's own writes update settings_modified and are not reloaded
+                if modified != self.settings_modified {
+                    self.settings_modified = modified;
+                    self.settings_reload_pending = true;
+                }
+            }
+            if !self.settings_reload_pending || self.settings_dialog || self.recording_hotkey.is_some() {
+                return;
+            }
+            self.settings_reload_pending = false;
+            log::info!("{} changed, reloading the settings", self.settings_path.display());
+            let result = resolve_settings(&self.settings_path);
+            if let Some(resolved) = self.manage_errors(result) {
+                self.use_settings(resolved);
+                let hotkeys = self.manage_errors(self.settings.get_hotkeys());
+                if let Some(hotkeys) = hotkeys {
+                    let result = self.hotkey_manager.register_all(hotkeys);
+                    self.manage_errors(result);
+                }
+            }
+        }
+
+        /// Closes the settings window, going back to the settings in use
+        fn discard_settings_edits(&mut self) {
+            if self.settings_dialog {
+                self.settings = self.settings_in_use.clone();
+                self.settings_dialog = false;
+            }
+        }
+
+        /// Starts using settings just resolved, remembering them apart from the copy which the
+        /// settings window edits
+        fn use_settings(&mut self, (settings, sources): (Settings, Sources)) {
+            set_log_level(settings.log_level);
+            self.settings_overrides = overridden(sources);
+            self.settings_in_use = settings.clone();
+            self.settings = settings;
+        }
+
+        /// Remembers the image before an edit, forgetting the oldest one past the limit and
+        /// everything which could be redone
+        fn push_undo(&mut self, snapshot: Screenshot) {
+            self.dirty = true;
+            self.schedule_autosave(false);
+            self.undo_stack.push(snapshot);
+            if self.undo_stack.len() > UNDO_LIMIT {
+                self.undo_stack.remove(0);
+            }
+            self.redo_stack.clear();
+        }
+
+        /// Called after undo or redo replaced the image, pending tool state belongs to the old one
+        fn history_restored(&mut self) {
+            self.dirty = true;
+            self.schedule_autosave(false);
+            self.starting_point = None;
+            self.text_edit_dialog = false;
+            self.magnify_source = None;
+            self.crop_rect = None;
+            self.crop_drag = None;
+            self.image_transformed();
+        }
+
+        /// Runs the command of a toolbar button, either clicked or triggered by its hotkey
+        pub fn perform_action(&mut self, action: Action, frame: &mut eframe::Frame) {
+            self.stop_hotkey_recording();
+            // a pasted image becomes part of the capture before anything else happens to it
+            if !matches!(action, Action::ZoomIn | Action::ZoomOut | Action::ZoomReset | Action::SwapColors) {
+                self.apply_overlay();
+            }
+            let editing = self.show_image && !self.error_dialog && !self.settings_dialog && !self.save_dialog && !self.adjust_dialog && !self.open_dialog;
+            match action {
+                Action::QuickScreenshot => {
+                    log::info!("Quick screenshot");
+                    let settings = self.settings_in_use.clone();
+                    let result = take_screenshot(Duration::from_secs(0), 0);
+                    if let Some(ss) = self.manage_errors(result) {
+                        self.add_to_history(&ss, CaptureSource::Screen(0));
+                        let metadata = save_metadata(&settings, &ss, &Caption::default());
+                        let result = ss.save_image(&PathBuf::from(&settings.path), ImageFormat::Png, metadata.as_ref());
+                        if let Some(saved) = self.manage_errors(result) {
+                            if settings.auto_copy {
+                                let result = ss.save_to_clipboard(settings.clipboard_mode, Some(&saved));
+                                self.manage_errors(result);
+                            }
+                        }
+                    }
+                }
+                Action::NewScreenshot => self.start_capture(frame, None),
+                Action::Preset(index) => {
+                    if let Some(preset) = self.settings.presets.get(index).cloned() {
+                        self.start_capture(frame, Some(preset));
+                    }
+                }
+                Action::Save => {
+                    if !self.any_dialog_open() {
+                        if self.drawing_mode == Some(DrawingMode::Shape) || self.drawing_mode == Some(DrawingMode::Magnify) {
+                            self.screenshot.rollback_changes();
+                            self.conversion();
+                        }
+                        self.previous_drawing_mode = self.drawing_mode;
+                        self.drawing_mode = None;
+                    }
+                    self.starting_point = None;
+                    self.saved_to_clipboard_dialog = false;
+                    self.text_edit_dialog = false;
+                    self.discard_settings_edits();
+                    self.adjust_dialog = false;
+                    if !self.save_dialog {
+                        self.save_dir = self.save_folder();
+                    }
+                    self.save_dialog = true;
+                }
+                Action::CopyToClipboard => {
+                    let flag = !self.any_dialog_open();
+                    self.discard_settings_edits();
+                    self.save_dialog = false;
+                    self.text_edit_dialog = false;
+                    self.adjust_dialog = false;
+                    let result = self.screenshot.save_to_clipboard(self.settings.clipboard_mode, self.last_saved.as_deref());
+                    if self.manage_errors(result).is_none() {
+                        return;
+                    }
+                    if flag {
+                        self.previous_drawing_mode = self.drawing_mode;
+                        self.drawing_mode = None;
+                    }
+                    self.saved_to_clipboard_dialog = true;
+                }
+                Action::Settings => {
+                    let flag = !self.any_dialog_open();
+                    self.saved_to_clipboard_dialog = false;
+                    self.save_dialog = false;
+                    self.text_edit_dialog = false;
+                    self.adjust_dialog = false;
+                    let result = read_settings_from_file(&self.settings_path);
+                    let result = self.manage_errors(result);
+                    if result.is_none() {
+                        return;
+                    }
+                    self.settings = result.unwrap();
+                    self.settings_imported = false;
+                    self.validate_settings(true);
+                    if flag {
+                        self.previous_drawing_mode = self.drawing_mode;
+                        self.drawing_mode = None;
+                    }
+                    self.settings_dialog = true;
+                }
+                Action::RotateLeft | Action::RotateRight if editing => {
+                    self.drawing_mode = None;
+                    self.text_edit_dialog = false;
+                    self.push_undo(self.screenshot.clone());
+                    let result = if action == Action::RotateLeft {
+                        self.screenshot.rotate_sx_90()
+                    } else {
+                        self.screenshot.rotate_dx_90()
+                    };
+                    if self.manage_errors(result).is_none() {
+                        return;
+                    }
+                    self.image_transformed();
+                }
+                Action::Adjust if editing => {
+                    self.text_edit_dialog = false;
+                    self.previous_drawing_mode = self.drawing_mode;
+                    self.drawing_mode = None;
+                    self.adjust_params.width = self.screenshot.get_width();
+                    self.adjust_params.height = self.screenshot.get_height();
+                    self.adjust_dialog = true;
+                }
+                Action::Crop if editing => self.select_tool(DrawingMode::Crop),
+                Action::Pen if editing => self.select_tool(DrawingMode::Paint),
+                Action::Highlight if editing => self.select_tool(DrawingMode::Highlight),
+                Action::Rubber if editing => self.select_tool(DrawingMode::Erase),
+                Action::Shapes if editing => self.select_tool(DrawingMode::Shape),
+                Action::Text if editing => self.select_tool(DrawingMode::Text),
+                Action::StepMarker if editing => self.select_tool(DrawingMode::StepMarker),
+                Action::Magnify if editing => self.select_tool(DrawingMode::Magnify),
+                Action::Spotlight if editing => self.select_tool(DrawingMode::Spotlight),
+                Action::Undo if editing => {
+                    if let Some(previous) = self.undo_stack.pop() {
+                        let current = std::mem::replace(&mut self.screenshot, previous);
+                        self.redo_stack.push(current);
+                        self.history_restored();
+                    }
+                }
+                Action::Redo if editing => {
+                    if let Some(next) = self.redo_stack.pop() {
+                        let current = std::mem::replace(&mut self.screenshot, next);
+                        self.undo_stack.push(current);
+                        self.history_restored();
+                    }
+                }
+                Action::ZoomIn if self.show_image => self.zoom = (self.zoom * 1.25).min(8.0),
+                Action::ZoomOut if self.show_image => self.zoom = (self.zoom / 1.25).max(0.25),
+                Action::ZoomReset => {
+                    self.zoom = 1.0;
+                    self.pan = Vec2::ZERO;
+                }
+                Action::SwapColors => std::mem::swap(&mut self.tool_color, &mut self.secondary_color),
+                Action::Open if !self.screenshot_taken => {
+                    if !self.any_dialog_open() {
+                        self.previous_drawing_mode = self.drawing_mode;
+                        self.drawing_mode = None;
+                    }
+                    self.saved_to_clipboard_dialog = false;
+                    self.discard_settings_edits();
+                    self.save_dialog = false;
+                    self.text_edit_dialog = false;
+                    self.adjust_dialog = false;
+                    if !self.open_dialog && self.open_path.is_empty() {
+                        self.open_path = self.save_folder();
+                    }
+                    self.open_dialog = true;
+                }
+                Action::CloseTab if editing => self.request_close_tab(self.active_tab),
+                Action::NextTab if editing => self.switch_tab((self.active_tab + 1) % self.tab_count()),
+                Action::History => {
+                    self.history_panel = !self.history_panel;
+                    if self.history_panel {
+                        self.refresh_saved_screenshots();
+                    }
+                }
+                Action::PasteNew if !self.any_dialog_open() && !self.screenshot_taken => {
+                    let image = clipboard_image();
+                    if let Some(image) = self.manage_errors(image) {
+                        let screenshot = Screenshot::from_image(image);
+                        self.add_to_history(&screenshot, CaptureSource::Clipboard);
+                        self.open_in_editor(screenshot, String::from("Pasted image"));
+                    }
+                }
+                Action::PasteOverlay if editing => {
+                    let image = clipboard_image();
+                    if let Some(image) = self.manage_errors(image) {
+                        // a bigger image is shrunk to fit in the capture, then centered
+                        let size = self.image_size();
+                        let scale = (size.x / image.width() as f32).min(size.y / image.height() as f32).min(1.0);
+                        let overlay_size = Vec2::new(image.width() as f32 * scale, image.height() as f32 * scale);
+                        let rect = Rect::from_min_size(((size - overlay_size) / 2.0).to_pos2(), overlay_size);
+                        self.overlay = Some(Overlay { image, rect, drag: None, texture: None });
+                        self.select_tool(DrawingMode::Overlay);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        /// What a bug report needs: versions, system, screens, settings in use and the end of the log
+        fn diagnostics(&self) -> String {
+            let screens: Vec<String> = get_screens().iter()
+                .map(|screen| format!("{}x{} at {}x scale", screen.display_info.width, screen.display_info.height, screen.display_info.scale_factor))
+                .collect();
+            let settings = serde_json::to_string_pretty(&self.settings).unwrap_or_default();
+            format!(
+                "Rusty Capture {}\nSystem: {} {}\nScreens: {}\nGlobal hotkeys: {}\nSettings file: {}\nLog file: {}\n\nSettings:\n{}\n\nLog:\n{}\n",
+                env!("CARGO_PKG_VERSION"),
+                std::env::consts::OS,
+                std::env::consts::ARCH,
+                if screens.is_empty() { String::from("none") } else { screens.join(", ") },
+                if self.hotkey_manager.has_global_hotkeys() { "available" } else { "not available" },
+                self.settings_path.display(),
+                log_path().map_or_else(|| String::from("none"), |path| path.display().to_string()),
+                settings,
+                recent_log(DIAGNOSTICS_LOG_LINES),
+            )
+        }
+
+        pub fn manage_errors<E>(&mut self, result: Result<E, RustyCaptureError>) -> Option<E> {
+            match result {
+                Ok(value) => Some(value),
+                Err(e) => {
+                    log::error!("{}", e);
+                    self.previous_drawing_mode_error = self.drawing_mode;
+                    self.drawing_mode = None;
+                    // the message says what failed, the hint what to do about it
+                    self.error_message = match e.hint() {
+                        Some(hint) => format!("{}\n{}", e, hint),
+                        None => e.to_string(),
+                    };
+                    self.error_dialog = true;
+                    None
+                }
+            }
+        }
+    }
+
+    impl App for ScreenshotStr {
+        fn on_close_event(&mut self) -> bool {
+            if !self.close_confirmed && self.unsaved_count() > 0 {
+                self.close_prompt = Some(ClosePrompt::App);
+                return false;
+            }
+            // nothing of this session is left to recover after a proper exit, the work of the
+            // sessions which were neither restored nor discarded is offered again
+            if let Some(thread) = self.autosave_thread.take() {
+                let _ = thread.join();
+            }
+            if let Some(session) = self.recovery.take() {
+                if let Err(e) = session.close() {
+                    log::warn!("Could not clear the recovery directory: {}", e);
+                }
+            }
+            if self.editor_state != self.saved_editor_state {
+                self.save_editor_state();
+            }
+            true
+        }
+
+        fn update(&mut self, ctx: &Context, frame: &mut eframe::Frame) {
+            self.sync_editor_state(frame);
+            self.reload_settings(ctx);
+            self.autosave();
+
+            //shortcuts
+            if let Ok(event) = GlobalHotKeyEvent::receiver().try_recv() {
+                if let Some(action) = self.hotkey_manager.get_action(event.id) {
+                    log::debug!("Global hotkey for {}", action);
+                    self.perform_action(action, frame);
+                }
+            }
+            // window shortcuts, left alone while typing in a text field or recording a hotkey
+            if !ctx.wants_keyboard_input() && self.recording_hotkey.is_none() {
+                let pressed = self.hotkey_manager.get_local_bindings().into_iter().find(|(_, accelerator)| {
+                    egui_shortcut(*accelerator).is_some_and(|shortcut| ctx.input_mut(|is| is.consume_shortcut(&shortcut)))
+                });
+                if let Some((action, _)) = pressed {
+                    self.perform_action(action, frame);
+                }
+            }
+
+            // pending step markers become part of the image as soon as another tool is selected
+            if self.drawing_mode.is_some() && self.drawing_mode != Some(DrawingMode::StepMarker) && self.drawing_mode != Some(DrawingMode::Pause) {
+                self.screenshot.commit_step_markers();
+            }
+            // and a pasted image as soon as its tool is left
+            if self.overlay.is_some() && self.drawing_mode != Some(DrawingMode::Overlay) {
+                self.apply_overlay();
+            }
+
+            //DIALOGS
+
+            // image files dropped on the window are opened, the first one only
+            let dropped = ctx.input(|is| is.raw.dropped_files.first().cloned());
+            if let Some(file) = dropped.filter(|_| !self.screenshot_taken && !self.any_dialog_open()) {
+                let result = match (&file.path, &file.bytes) {
+                    (Some(path), _) => Some(Screenshot::from_path(path)),
+                    (None, Some(bytes)) => Some(Screenshot::from_bytes(bytes)),
+                    (None, None) => None,
+                };
+                if let Some(screenshot) = result.and_then(|result| self.manage_errors(result)) {
+                    let title = file.path.as_deref().map_or(file.name.clone(), file_title);
+                    self.open_in_editor(screenshot, title);
+                }
+            }
+            if ctx.input(|is| !is.raw.hovered_files.is_empty()) {
+                let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Foreground, egui::Id::new("drop_hint")));
+                let screen = ctx.screen_rect();
+                painter.rect_filled(screen, 0.0, Color32::from_black_alpha(160));
+                painter.text(screen.center(), egui::Align2::CENTER_CENTER, "Drop the image to open it", egui::FontId::proportional(24.0), Color32::WHITE);
+            }
+
+            // RECOVERY_DIALOG
+            if !self.recovered.is_empty() {
+                Window::new("Recover unsaved work")
+                    .collapsible(false)
+                    .resizable(false)
+                    .show(ctx, |ui| {
+                        ui.set_enabled(!self.error_dialog && !self.screenshot_taken);
+
+                        let entries: Vec<&str> = self.recovered.iter().flat_map(|session| &session.entries).map(|entry| entry.title.as_str()).collect();
+                        ui.label(format!("Rusty Capture was not closed properly, {} captures had unsaved changes:", entries.len()));
+                        for title in entries {
+                            ui.label(format!("\u{2022} {}", title));
+                        }
+                        ui.horizontal(|ui| {
+                            if ui.button("Restore").clicked() {
+                                self.restore_recovered();
+                            }
+                            if ui.button("Discard").clicked() {
+                                self.discard_recovered();
+                            }
+                        });
+                    });
+            }
+
+            // UNSAVED_CHANGES_DIALOG
+            if let Some(prompt) = self.close_prompt {
+                Window::new("Unsaved changes")
+                    .collapsible(false)
+                    .resizable(false)
+                    .show(ctx, |ui| {
+                        ui.set_enabled(!self.error_dialog);
+
+                        match prompt {
+                            ClosePrompt::Tab => ui.label(format!("\"{}\" has unsaved changes.", self.title)),
+                            ClosePrompt::App => ui.label(format!("{} open captures have unsaved changes.", self.unsaved_count())),
+                        };
+                        let mut close = false;
+                        ui.horizontal(|ui| {
+                            let save = ui.button(if prompt == ClosePrompt::App { "Save all" } else { "Save" })
+                                .on_hover_text(format!("Save in {}", self.save_folder()));
+                            if save.clicked() {
+                                close = self.save_unsaved(prompt == ClosePrompt::App);
+                            }
+                            if ui.button("Discard").clicked() {
+                                close = true;
+                            }
+                            if ui.button("Cancel").clicked() {
+                                self.close_prompt = None;
+                            }
+                        });
+                        if close {
+                            self.close_prompt = None;
+                            match prompt {
+                                ClosePrompt::Tab => self.close_tab(),
+                                ClosePrompt::App => {
+                                    self.close_confirmed = true;
+                                    frame.close();
+                                }
+                            }
+                        }
+                    });
+            }
+
+            // OPEN_DIALOG
+            if self.open_dialog {
+                Window::new("Open Image")
+                    .collapsible(false)
+                    .resizable(false)
+                    .show(ctx, |ui| {
+                        ui.set_enabled(!self.error_dialog);
+
+                        ui.horizontal(|ui| {
+                            ui.label("File");
+                            ui.add(TextEdit::singleline(&mut self.open_path).desired_width(300.0));
+                        });
+                        ui.horizontal(|ui| {
+                            if ui.button("Cancel").clicked() {
+                                self.drawing_mode = self.previous_drawing_mode;
+                                self.open_dialog = false;
+                            }
+                            if ui.button("Open").clicked() {
+                                let result = Screenshot::from_path(&PathBuf::from(&self.open_path));
+                                if let Some(screenshot) = self.manage_errors(result) {
+                                    self.open_dialog = false;
+                                    self.open_in_editor(screenshot, file_title(Path::new(&self.open_path)));
+                                }
+                            }
+                        });
+                    });
+            }
+
+            // SAVE_DIALOG
+            if self.save_dialog {
+                Window::new("Save Screenshot")
+                    .collapsible(false)
+                    .resizable(false)
+                    .show(ctx, |ui| {
+                        ui.set_enabled(!self.error_dialog);
+
+                        ui.horizontal(|ui| {
+                            ui.label("Folder");
+                            ui.add(TextEdit::singleline(&mut self.save_dir));
+                        });
+                        ui.add_enabled_ui(!self.settings.strip_metadata, |ui| {
+                            Grid::new("caption_grid").num_columns(2).show(ui, |ui| {
+                                ui.label("Title");
+                                ui.add(TextEdit::singleline(&mut self.caption.title));
+                                ui.end_row();
+                                ui.label("Description");
+                                ui.add(TextEdit::multiline(&mut self.caption.description).desired_rows(2));
+                                ui.end_row();
+                            });
+                        }).response.on_disabled_hover_text("Metadata is stripped from saved files in the settings");
+
+                        //close button
+                        ui.horizontal(|ui| {
+                            ui.label("Save as?");
+                            // the format used last time is highlighted
+                            for (format, name) in [(ImageFormat::Png, "PNG"), (ImageFormat::Jpeg, "JPG"), (ImageFormat::Gif, "GIF")] {
+                                if ui.selectable_label(self.format == format, name).clicked() {
+                                    self.format = format;
+                                    //error handling
+                                    let metadata = save_metadata(&self.settings, &self.screenshot, &self.caption);
+                                    let result = self.screenshot.save_image(&PathBuf::from(&self.save_dir), self.format, metadata.as_ref());
+                                    self.last_saved = self.manage_errors(result);
+                                    if self.last_saved.is_none() {
+                                        return;
+                                    }
+                                    self.target_format = format;
+                                    self.target_dir = PathBuf::from(&self.save_dir);
+                                    self.dirty = false;
+                                    self.schedule_autosave(true);
+                                    if self.settings.auto_copy {
+                                        let result = self.screenshot.save_to_clipboard(self.settings.clipboard_mode, self.last_saved.as_deref());
+                                        self.manage_errors(result);
+                                    }
+                                    self.editor_state.last_save_dir = self.save_dir.clone();
+                                    if self.history_panel {
+                                        self.refresh_saved_screenshots();
+                                    }
+                                    self.save_dialog = false;
+                                    self.drawing_mode = self.previous_drawing_mode;
+                                }
+                            }
+                        });
+
+                        //close
+                        ui.horizontal(|ui| {
+                            if ui.button("Cancel").clicked() {
+                                self.drawing_mode = self.previous_drawing_mode;
+                                self.save_dialog = false;
+                            }
+                        });
+                    });
+            }
+
+
+            if self.saved_to_clipboard_dialog {
+                Window::new("Save Screenshot")
+                    .collapsible(false)
+                    .title_bar(false)
+                    .resizable(false)
+                    .movable(false)
+                    .show(ctx, |ui| {
+                        ui.set_enabled(!self.error_dialog);
+
+                        if ui.label("Saved to clipboard!").clicked_elsewhere() {
+                            self.saved_to_clipboard_dialog = false;
+                        };
+                        if ui.button("Ok").clicked() {
+                            self.saved_to_clipboard_dialog = false;
+                            self.drawing_mode = self.previous_drawing_mode;
+                        }
+                    });
             }
 
             // SETTING_DIALOG
@@ -638,110 +2377,300 @@ pub mod state_module {
                     .show(ctx, |ui| {
                         ui.set_enabled(!self.error_dialog);
 
+                        // values set at launch win over the file edited here
+                        if !self.settings_overrides.is_empty() {
+                            ui.colored_label(Color32::YELLOW, format!("Overridden while running: {}", self.settings_overrides.join(", ")));
+                        }
+
+                        // the next combination pressed while recording becomes the hotkey
+                        if let Some(action) = self.recording_hotkey {
+                            let pressed = ui.input(|i| i.events.iter().find_map(|event| match event {
+                                egui::Event::Key { key, pressed: true, modifiers, .. } => Some((*key, *modifiers)),
+                                _ => None,
+                            }));
+                            if let Some((key, modifiers)) = pressed {
+                                match key {
+                                    Key::Escape if modifiers.is_none() => {}
+                                    Key::Backspace | Key::Delete if modifiers.is_none() => {
+                                        self.settings.hotkey_mut(action).clear();
+                                    }
+                                    _ => {
+                                        *self.settings.hotkey_mut(action) = accelerator_name(modifiers, key);
+                                    }
+                                }
+                                self.stop_hotkey_recording();
+                                self.validate_settings(true);
+                            }
+                        }
+
                         ui.label("Change Hotkeys");
+                        ui.label("Leave a hotkey empty to unbind its action");
+                        let mut changed = false;
+                        Grid::new("hotkeys_grid").num_columns(5).show(ui, |ui| {
+                            // global hotkeys first, then the ones working only while the window is focused
+                            let mut actions = Action::ALL;
+                            actions.sort_by_key(|action| !action.is_global());
+                            let mut section = None;
+                            for action in actions {
+                                if section != Some(action.is_global()) {
+                                    section = Some(action.is_global());
+                                    ui.strong(if action.is_global() { "Global hotkeys" } else { "Window shortcuts" });
+                                    ui.end_row();
+                                }
+                                ui.label(action.to_string());
+                                changed |= self.hotkey_row(ui, action);
+                                ui.end_row();
+                            }
+                        });
                         ui.horizontal(|ui| {
-                            ui.label("Quick Screenshot");
-                            ui.label("CTRL + ");
-                            ui.add(TextEdit::singleline(&mut self.settings.quick)
-                                .char_limit(1)
-                                .desired_width(ui.available_width() / 4.0));
+                            ui.label("Path");
+                            //turn pathbuf into string
+                            changed |= ui.add(TextEdit::singleline(&mut self.settings.path)).changed();
+                            if let Some(error) = self.settings_errors.get(&SettingsField::Path) {
+                                ui.colored_label(Color32::RED, error);
+                            }
                         });
+
                         ui.horizontal(|ui| {
-                            ui.label("New Screenshot");
-                            ui.label("CTRL + ");
-                            ui.add(TextEdit::singleline(&mut self.settings.new_screenshot)
-                                .char_limit(1)
-                                .desired_width(ui.available_width() / 4.0));
+                            ComboBox::from_label("Copy as")
+                                .selected_text(self.settings.clipboard_mode.to_string())
+                                .show_ui(ui, |ui| {
+                                    for mode in ClipboardMode::ALL {
+                                        changed |= ui.selectable_value(&mut self.settings.clipboard_mode, mode, mode.to_string()).changed();
+                                    }
+                                });
+                            ui.checkbox(&mut self.settings.auto_copy, "Copy automatically after capturing and saving");
                         });
+                        ui.checkbox(&mut self.settings.strip_metadata, "Strip metadata from saved files")
+                            .on_hover_text("Saves PNG and JPEG files without when and where they were captured, the edits applied, their title and description");
                         ui.horizontal(|ui| {
-                            ui.label("Save Screenshot");
-                            ui.label("CTRL + ");
-                            ui.add(TextEdit::singleline(&mut self.settings.save)
-                                .char_limit(1)
-                                .desired_width(ui.available_width() / 4.0));
+                            ui.label("Autosave every");
+                            ui.add(egui::DragValue::new(&mut self.settings.autosave_interval).clamp_range(0..=3600).suffix(" s"))
+                                .on_hover_text("Unsaved captures are written to the recovery directory, 0 disables the autosave");
                         });
                         ui.horizontal(|ui| {
-                            ui.label("Pen tool");
-                            ui.label("CTRL + ");
-                            ui.add(TextEdit::singleline(&mut self.settings.pen)
-                                .char_limit(1)
-                                .desired_width(ui.available_width() / 4.0));
+                            ui.label("History size");
+                            ui.add(egui::DragValue::new(&mut self.settings.history_limit).clamp_range(0..=500))
+                                .on_hover_text("How many captures are kept, 0 disables the history");
                         });
                         ui.horizontal(|ui| {
-                            ui.label("Rubber tool");
-                            ui.label("CTRL + ");
-                            ui.add(TextEdit::singleline(&mut self.settings.rubber)
-                                .char_limit(1)
-                                .desired_width(ui.available_width() / 4.0));
+                            ComboBox::from_label("Log level")
+                                .selected_text(self.settings.log_level.to_string())
+                                .show_ui(ui, |ui| {
+                                    for level in LogLevel::ALL {
+                                        ui.selectable_value(&mut self.settings.log_level, level, level.to_string());
+                                    }
+                                });
+                            if ui.button("Copy diagnostics").on_hover_text("Copies the versions, the system and the end of the log for a bug report").clicked() {
+                                ui.output_mut(|o| o.copied_text = self.diagnostics());
+                            }
+                        });
+
+                        ui.separator();
+                        ui.label("Capture presets");
+                        let mut removed = None;
+                        egui::ScrollArea::vertical().max_height(250.0).show(ui, |ui| {
+                            for index in 0..self.settings.presets.len() {
+                                let mut title = self.settings.presets[index].name.clone();
+                                if self.settings_errors.contains_key(&SettingsField::Preset(index)) {
+                                    title.push_str(" \u{26A0}");
+                                }
+                                egui::CollapsingHeader::new(title).id_source(("preset", index)).show(ui, |ui| {
+                                    if let Some(error) = self.settings_errors.get(&SettingsField::Preset(index)) {
+                                        ui.colored_label(Color32::RED, error);
+                                    }
+                                    changed |= preset_editor(ui, index, &mut self.settings.presets[index]);
+                                    ui.horizontal(|ui| {
+                                        ui.label("Hotkey");
+                                        changed |= self.hotkey_row(ui, Action::Preset(index));
+                                    });
+                                    if ui.button("Remove preset").clicked() {
+                                        removed = Some(index);
+                                    }
+                                });
+                            }
                         });
+                        if let Some(index) = removed {
+                            self.stop_hotkey_recording();
+                            self.settings.presets.remove(index);
+                            changed = true;
+                        }
+                        if ui.button("Add preset").clicked() {
+                            self.settings.presets.push(CapturePreset::default());
+                            changed = true;
+                        }
+
+                        ui.separator();
                         ui.horizontal(|ui| {
-                            ui.label("Path");
-                            //turn pathbuf into string
-                            ui.add(TextEdit::singleline(&mut self.settings.path));
+                            ui.label("Settings file");
+                            ui.add(TextEdit::singleline(&mut self.settings_transfer_path));
+                            if ui.button("Export").clicked() {
+                                let result = export_settings(&PathBuf::from(&self.settings_transfer_path), &self.settings);
+                                self.manage_errors(result);
+                            }
+                            if ui.button("Import").clicked() {
+                                self.stop_hotkey_recording();
+                                let result = import_settings(&PathBuf::from(&self.settings_transfer_path), &self.settings, self.settings_transfer_merge);
+                                if let Some(settings) = self.manage_errors(result) {
+                                    self.settings = settings;
+                                    self.settings_imported = true;
+                                    changed = true;
+                                }
+                            }
+                            ui.checkbox(&mut self.settings_transfer_merge, "Merge")
+                                .on_hover_text("Keep the settings missing from the file, otherwise they are reset to their defaults");
                         });
+                        if changed {
+                            self.validate_settings(false);
+                        }
                         //close
                         ui.horizontal(|ui| {
                             if ui.button("Cancel").clicked() {
+                                self.stop_hotkey_recording();
+                                self.discard_settings_edits();
                                 self.drawing_mode = self.previous_drawing_mode;
-                                self.settings_dialog = false;
                             }
-                            if ui.button("Save").clicked() {
-                                let result = write_settings_to_file("settings.json".to_string(), &self.settings);
-                                if self.manage_errors(result).is_none() {
+                            let valid = self.settings_errors.is_empty() && self.recording_hotkey.is_none();
+                            if ui.add_enabled(valid, Button::new("Save")).clicked() {
+                                self.validate_settings(true);
+                                if !self.settings_errors.is_empty() {
                                     return;
                                 }
-                                let startup_settings = read_settings_from_file("settings.json".to_string());
-                                let result = self.manage_errors(startup_settings);
-                                if result.is_none() {
-                                    return;
-                                }
-                                let startup_settings = result.unwrap();
-                                //KEY_NEW_SCREENSHOT
-                                let key_new_screenshot = startup_settings.get_new_screenshot_hotkey();
-                                let result = self.manage_errors(key_new_screenshot);
-                                if result.is_none() {
-                                    return;
+                                // imported tool styles and options replace the ones in use
+                                if self.settings_imported {
+                                    self.apply_editor_state(self.settings.editor.clone());
                                 }
-                                let key_new_screenshot = result.unwrap();
-                                let result = self.hotkey_manager.register_new_hotkey(Some(Modifiers::CONTROL), key_new_screenshot, KeyType::NewScreenshot);
+                                self.settings.editor = self.editor_state.clone();
+                                let result = write_settings_to_file(&self.settings_path, &self.settings);
                                 if self.manage_errors(result).is_none() {
                                     return;
                                 }
-                                //KEY_PEN
-                                let key_pen = startup_settings.get_pen_hotkey();
-                                let result = self.manage_errors(key_pen);
-                                if result.is_none() {
+                                self.settings_modified = settings_modified(&self.settings_path);
+                                let resolved = resolve_written_settings(&self.settings);
+                                let Some(resolved) = self.manage_errors(resolved) else {
                                     return;
-                                }
-                                let key_pen = result.unwrap();
-                                let result = self.hotkey_manager.register_new_hotkey(Some(Modifiers::CONTROL), key_pen, KeyType::Pen);
-                                if self.manage_errors(result).is_none() {
-                                    return;
-                                }
-                                //KEY_RUBBER
-                                let key_rubber = startup_settings.get_rubber_hotkey();
-                                let result = self.manage_errors(key_rubber);
+                                };
+                                self.use_settings(resolved);
+                                let result = self.history.prune(self.settings.history_limit);
+                                self.manage_errors(result);
+                                let hotkeys = self.settings.get_hotkeys();
+                                let result = self.manage_errors(hotkeys);
                                 if result.is_none() {
                                     return;
                                 }
-                                let key_rubber = result.unwrap();
-                                let result = self.hotkey_manager.register_new_hotkey(Some(Modifiers::CONTROL), key_rubber, KeyType::Rubber);
+                                let result = self.hotkey_manager.register_all(result.unwrap());
                                 if self.manage_errors(result).is_none() {
                                     return;
                                 }
-                                //KEY_QUICK
-                                let key_quick = startup_settings.get_quick_hotkey();
-                                let result = self.manage_errors(key_quick);
-                                if result.is_none() {
-                                    return;
+                                self.drawing_mode = self.previous_drawing_mode;
+                                self.settings_dialog = false;
+                            }
+                        });
+                    });
+            }
+
+            // ADJUST_DIALOG
+            if self.adjust_dialog {
+                Window::new("Adjust Image")
+                    .collapsible(false)
+                    .resizable(false)
+                    .show(ctx, |ui| {
+                        ui.set_enabled(!self.error_dialog);
+
+                        // buttons fire on release, the image is only copied for undo then
+                        let snapshot = ui.input(|is| is.pointer.any_released()).then(|| self.screenshot.clone());
+                        let params = &mut self.adjust_params;
+                        let fill = [params.fill_color[0], params.fill_color[1], params.fill_color[2], 255];
+                        let mut result: Option<Result<(), RustyCaptureError>> = None;
+                        ui.horizontal(|ui| {
+                            ui.label("Transform");
+                            if ui.button("Flip \u{2194}").clicked() { result = Some(self.screenshot.flip_horizontal()); }
+                            if ui.button("Flip \u{2195}").clicked() { result = Some(self.screenshot.flip_vertical()); }
+                            if ui.button("Rotate 180°").clicked() { result = Some(self.screenshot.rotate_180()); }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Rotate");
+                            ui.add(egui::DragValue::new(&mut params.angle).clamp_range(-360.0..=360.0).suffix("°"));
+                            ui.label("Background");
+                            ui.color_edit_button_srgb(&mut params.fill_color);
+                            if ui.button("Apply").clicked() { result = Some(self.screenshot.rotate(params.angle, fill)); }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Resize");
+                            ui.selectable_value(&mut params.scale_by_percentage, true, "%");
+                            ui.selectable_value(&mut params.scale_by_percentage, false, "px");
+                            if params.scale_by_percentage {
+                                ui.add(egui::DragValue::new(&mut params.percentage).clamp_range(1.0..=800.0).suffix("%"));
+                            } else {
+                                ui.add(egui::DragValue::new(&mut params.width).clamp_range(1..=20000));
+                                ui.label("x");
+                                ui.add(egui::DragValue::new(&mut params.height).clamp_range(1..=20000));
+                            }
+                            ComboBox::from_id_source("resize_filter")
+                                .selected_text(filter_name(params.filter))
+                                .show_ui(ui, |ui| {
+                                    for filter in [FilterType::Nearest, FilterType::Triangle, FilterType::CatmullRom, FilterType::Gaussian, FilterType::Lanczos3] {
+                                        ui.selectable_value(&mut params.filter, filter, filter_name(filter));
+                                    }
+                                });
+                            if ui.button("Apply").clicked() {
+                                if params.scale_by_percentage {
+                                    result = Some(self.screenshot.scale(params.percentage, params.filter));
+                                } else {
+                                    result = Some(self.screenshot.resize_to(params.width, params.height, params.filter));
                                 }
-                                let key_quick = result.unwrap();
-                                let result = self.hotkey_manager.register_new_hotkey(Some(Modifiers::CONTROL), key_quick, KeyType::Quick);
-                                if self.manage_errors(result).is_none() {
-                                    return;
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Padding");
+                            for (side, value) in ["L", "T", "R", "B"].iter().zip(params.padding.iter_mut()) {
+                                ui.label(*side);
+                                ui.add(egui::DragValue::new(value).clamp_range(0..=2000));
+                            }
+                            if ui.button("Apply").clicked() {
+                                let [left, top, right, bottom] = params.padding;
+                                result = Some(self.screenshot.pad(left, top, right, bottom, fill));
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Colors");
+                            if ui.button("Grayscale").clicked() { result = Some(self.screenshot.grayscale()); }
+                            if ui.button("Invert").clicked() { result = Some(self.screenshot.invert()); }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Brightness");
+                            ui.add(Slider::new(&mut params.brightness, -255..=255));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Contrast");
+                            ui.add(Slider::new(&mut params.contrast, -100.0..=100.0));
+                            if ui.button("Apply").clicked() {
+                                result = Some(self.screenshot.brightness_contrast(params.brightness, params.contrast));
+                                params.brightness = 0;
+                                params.contrast = 0.0;
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Sharpen");
+                            ui.add(Slider::new(&mut params.sharpen_sigma, 0.5..=5.0));
+                            if ui.button("Apply").clicked() { result = Some(self.screenshot.sharpen(params.sharpen_sigma, 1)); }
+                        });
+                        if let Some(result) = result {
+                            if self.manage_errors(result).is_some() {
+                                if let Some(snapshot) = snapshot {
+                                    self.push_undo(snapshot);
                                 }
+                                self.adjust_params.width = self.screenshot.get_width();
+                                self.adjust_params.height = self.screenshot.get_height();
+                                self.image_transformed();
+                            }
+                        }
+
+                        //close
+                        ui.horizontal(|ui| {
+                            if ui.button("Close").clicked() {
                                 self.drawing_mode = self.previous_drawing_mode;
-                                self.settings_dialog = false;
+                                self.adjust_dialog = false;
                             }
                         });
                     });
@@ -759,11 +2688,32 @@ pub mod state_module {
                                 self.drawing_mode = self.previous_drawing_mode_error;
                                 self.error_dialog = false;
                             }
+                            if ui.button("Copy diagnostics").clicked() {
+                                ui.output_mut(|o| o.copied_text = self.diagnostics());
+                            }
                         });
                     });
             }
             self.check_minimization(frame);
 
+            // what does not work in this session
+            if !self.degraded.is_empty() {
+                TopBottomPanel::top("degraded_banner").show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.vertical(|ui| {
+                            for message in &self.degraded {
+                                ui.colored_label(ui.visuals().warn_fg_color, format!("\u{26A0} {}", message));
+                            }
+                        });
+                        ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                            if ui.button("Dismiss").clicked() {
+                                self.degraded.clear();
+                            }
+                        });
+                    });
+                });
+            }
+
             // header of the app
             TopBottomPanel::top("header").frame(
                 Frame {
@@ -782,14 +2732,14 @@ pub mod state_module {
                 self.upper_panel_size = ui.available_size();
 
                 ui.horizontal(|ui| {
-                    if ui.button("New Screenshot")
-                        .on_hover_text(format!("CTRL + {}", self.settings.new_screenshot))
-                        .clicked() {
-                        self.window_size = frame.info().window_info.size;
-                        self.window_pos = frame.info().window_info.position.unwrap();
-                        self.screenshot_taken = true;
-                        let result = self.hotkey_manager.set_active_shortcuts(ActiveShortcuts::Pause);
-                        self.manage_errors(result);
+                    let response = ui.button("\u{1F4C2}");
+                    if self.shortcut_hint(response, Action::Open).clicked() {
+                        self.perform_action(Action::Open, frame);
+                    }
+
+                    let response = ui.button("New Screenshot");
+                    if self.shortcut_hint(response, Action::NewScreenshot).clicked() {
+                        self.perform_action(Action::NewScreenshot, frame);
                     }
 
                     ui.separator();
@@ -824,59 +2774,83 @@ pub mod state_module {
                             }
                         });
 
+                    // capture presets
+                    ui.menu_button("Presets", |ui| {
+                        for index in 0..self.settings.presets.len() {
+                            let response = ui.button(&self.settings.presets[index].name);
+                            if self.shortcut_hint(response, Action::Preset(index)).clicked() {
+                                ui.close_menu();
+                                self.perform_action(Action::Preset(index), frame);
+                            }
+                        }
+                    });
+
+                    // clipboard images
+                    ui.menu_button("Paste", |ui| {
+                        for action in [Action::PasteNew, Action::PasteOverlay] {
+                            let enabled = action == Action::PasteNew || self.show_image;
+                            let response = ui.add_enabled(enabled, Button::new(action.to_string()));
+                            if self.shortcut_hint(response, action).clicked() {
+                                ui.close_menu();
+                                self.perform_action(action, frame);
+                            }
+                        }
+                    });
+
                     ui.separator();
 
+                    // history panel
+                    let response = ui.selectable_label(self.history_panel, "\u{1F558}");
+                    if self.shortcut_hint(response, Action::History).clicked() {
+                        self.perform_action(Action::History, frame);
+                    }
+
                     // save button
-                    if ui.button("\u{1F4BE}")
-                        .on_hover_text(format!("CTRL + {}", self.settings.save))
-                        .clicked() {
-                        if !self.saved_to_clipboard_dialog && !self.settings_dialog && !self.save_dialog {
-                            self.previous_drawing_mode = self.drawing_mode;
-                            self.drawing_mode = None;
-                        }
-                        self.settings_dialog = false;
-                        self.saved_to_clipboard_dialog = false;
-                        self.text_edit_dialog = false;
-                        self.save_dialog = true;
+                    let response = ui.button("\u{1F4BE}");
+                    if self.shortcut_hint(response, Action::Save).clicked() {
+                        self.perform_action(Action::Save, frame);
                     }
 
                     // save to clipboard button
-                    if ui.button("\u{1F4CB}").clicked() {
-                        let flag = !self.saved_to_clipboard_dialog && !self.settings_dialog && !self.save_dialog;
-                        self.settings_dialog = false;
-                        self.save_dialog = false;
-                        self.text_edit_dialog = false;
-                        let result = self.screenshot.save_to_clipboard();
-                        if self.manage_errors(result).is_none() {
-                            return;
-                        }
-                        if flag {
-                            self.previous_drawing_mode = self.drawing_mode;
-                            self.drawing_mode = None;
-                        }
-                        self.saved_to_clipboard_dialog = true;
+                    let response = ui.button("\u{1F4CB}");
+                    if self.shortcut_hint(response, Action::CopyToClipboard).clicked() {
+                        self.perform_action(Action::CopyToClipboard, frame);
                     }
                     // settings button in the top right corner
                     ui.with_layout(Layout::top_down(Align::RIGHT), |ui| {
-                        if ui.button("\u{2699}").clicked() {
-                            let flag = !self.saved_to_clipboard_dialog && !self.settings_dialog && !self.save_dialog;
-                            self.saved_to_clipboard_dialog = false;
-                            self.save_dialog = false;
-                            self.text_edit_dialog = false;
-                            let result = read_settings_from_file("settings.json".to_string());
-                            let result = self.manage_errors(result);
-                            if result.is_none() {
-                                return;
-                            }
-                            self.settings = result.unwrap();
-                            if flag {
-                                self.previous_drawing_mode = self.drawing_mode;
-                                self.drawing_mode = None;
-                            }
-                            self.settings_dialog = true;
+                        let response = ui.button("\u{2699}");
+                        if self.shortcut_hint(response, Action::Settings).clicked() {
+                            self.perform_action(Action::Settings, frame);
                         }
                     });
                 });
+
+                // tabs of the open captures, a dot marks unsaved changes
+                if self.show_image {
+                    let enabled = !self.any_dialog_open() && self.close_prompt.is_none() && !self.screenshot_taken;
+                    let (mut selected, mut closed) = (None, None);
+                    ui.add_enabled_ui(enabled, |ui| {
+                        ui.horizontal_wrapped(|ui| {
+                            for index in 0..self.tab_count() {
+                                let (title, dirty) = self.tab_title(index);
+                                let title = if dirty { format!("{} \u{2022}", title) } else { title.to_string() };
+                                if ui.selectable_label(index == self.active_tab, title).clicked() {
+                                    selected = Some(index);
+                                }
+                                if ui.small_button("\u{2715}").on_hover_text("Close").clicked() {
+                                    closed = Some(index);
+                                }
+                                ui.separator();
+                            }
+                        });
+                    });
+                    if let Some(index) = selected {
+                        self.switch_tab(index);
+                    }
+                    if let Some(index) = closed {
+                        self.request_close_tab(index);
+                    }
+                }
             });
 
             // footer of the app
@@ -891,86 +2865,102 @@ pub mod state_module {
                 )
                 .resizable(false)
                 .show(ctx, |ui| {
-                    ui.set_enabled(!self.error_dialog && !self.settings_dialog && !self.save_dialog);
+                    ui.set_enabled(!self.error_dialog && !self.settings_dialog && !self.save_dialog && !self.adjust_dialog && !self.open_dialog);
 
                     if self.show_image {
                         ui.horizontal(|ui| {
                             // rotate left
-                            if ui.button("\u{27F3}").clicked() {
-                                self.drawing_mode = None;
-                                self.text_edit_dialog = false;
-                                let result = self.screenshot.rotate_sx_90();
-                                if self.manage_errors(result).is_none() {
-                                    return;
-                                }
-                                self.convert_image();
-                                self.show_image = true;
+                            let response = ui.button("\u{27F3}");
+                            if self.shortcut_hint(response, Action::RotateLeft).clicked() {
+                                self.perform_action(Action::RotateLeft, frame);
                             }
 
                             // rotate right
-                            if ui.button("\u{27F2}").clicked() {
-                                self.drawing_mode = None;
-                                self.text_edit_dialog = false;
-                                let result = self.screenshot.rotate_dx_90();
-                                if self.manage_errors(result).is_none() {
-                                    return;
-                                }
-                                self.convert_image();
-                                self.show_image = true;
+                            let response = ui.button("\u{27F2}");
+                            if self.shortcut_hint(response, Action::RotateRight).clicked() {
+                                self.perform_action(Action::RotateRight, frame);
+                            }
+
+                            // adjust
+                            let response = ui.button("\u{1F527}");
+                            if self.shortcut_hint(response, Action::Adjust).clicked() {
+                                self.perform_action(Action::Adjust, frame);
                             }
 
                             // crop
-                            if ui.button("\u{2702}").clicked() {
-                                self.text_edit_dialog = false;
-                                let result = self.screenshot.save_intermediate_image();
-                                if self.manage_errors(result).is_none() {
-                                    return;
-                                }
-                                self.toggle_drawing_mode(DrawingMode::Crop);
-                                self.crop_screenshot_tmp = self.screenshot.clone();
+                            let response = ui.button("\u{2702}");
+                            if self.shortcut_hint(response, Action::Crop).clicked() {
+                                self.perform_action(Action::Crop, frame);
                             }
 
                             // draw
-                            if ui.button("\u{270F}")
-                                .on_hover_text(format!("CTRL + {}", self.settings.pen))
-                                .clicked() {
-                                self.text_edit_dialog = false;
-                                self.toggle_drawing_mode(DrawingMode::Paint);
+                            let response = ui.button("\u{270F}");
+                            if self.shortcut_hint(response, Action::Pen).clicked() {
+                                self.perform_action(Action::Pen, frame);
                             }
 
                             // highlight
-                            if ui.button("\u{1F526}").clicked() {
-                                self.text_edit_dialog = false;
-                                self.toggle_drawing_mode(DrawingMode::Highlight);
+                            let response = ui.button("\u{1F526}");
+                            if self.shortcut_hint(response, Action::Highlight).clicked() {
+                                self.perform_action(Action::Highlight, frame);
                             }
 
                             // erase
-                            if ui.button("\u{1F4D8}")
-                                .on_hover_text(format!("CTRL + {}", self.settings.rubber))
-                                .clicked() {
-                                self.text_edit_dialog = false;
-                                self.toggle_drawing_mode(DrawingMode::Erase);
+                            let response = ui.button("\u{1F4D8}");
+                            if self.shortcut_hint(response, Action::Rubber).clicked() {
+                                self.perform_action(Action::Rubber, frame);
                             }
 
                             // shapes
-                            if ui.button("\u{2B1F}").clicked() {
-                                self.text_edit_dialog = false;
-                                let result = self.screenshot.save_intermediate_image();
-                                if self.manage_errors(result).is_none() {
-                                    return;
-                                }
-                                self.toggle_drawing_mode(DrawingMode::Shape);
+                            let response = ui.button("\u{2B1F}");
+                            if self.shortcut_hint(response, Action::Shapes).clicked() {
+                                self.perform_action(Action::Shapes, frame);
                             }
 
                             // text
-                            if ui.button("\u{1F1F9}").clicked() {
-                                self.text_edit_dialog = false;
-                                self.toggle_drawing_mode(DrawingMode::Text);
+                            let response = ui.button("\u{1F1F9}");
+                            if self.shortcut_hint(response, Action::Text).clicked() {
+                                self.perform_action(Action::Text, frame);
+                            }
+
+                            // step marker
+                            let response = ui.button("\u{2460}");
+                            if self.shortcut_hint(response, Action::StepMarker).clicked() {
+                                self.perform_action(Action::StepMarker, frame);
+                            }
+
+                            // magnify
+                            let response = ui.button("\u{1F50D}");
+                            if self.shortcut_hint(response, Action::Magnify).clicked() {
+                                self.perform_action(Action::Magnify, frame);
+                            }
+
+                            // spotlight
+                            let response = ui.button("\u{1F506}");
+                            if self.shortcut_hint(response, Action::Spotlight).clicked() {
+                                self.perform_action(Action::Spotlight, frame);
                             }
 
                             // selected tool
-                            if self.drawing_mode.is_some() {
-                                ui.label(self.drawing_mode.unwrap().to_string());
+                            if let Some(mode) = self.drawing_mode {
+                                ui.label(mode.to_string());
+                            }
+                            if self.drawing_mode == Some(DrawingMode::Overlay) {
+                                if ui.button("Apply").on_hover_text("Enter").clicked() {
+                                    self.apply_overlay();
+                                }
+                                if ui.button("Discard").on_hover_text("Escape").clicked() {
+                                    self.overlay = None;
+                                    self.drawing_mode = None;
+                                }
+                            }
+
+                            // zoom level, clicking it goes back to the fitted image
+                            if (self.zoom - 1.0).abs() > f32::EPSILON || self.pan != Vec2::ZERO {
+                                let response = ui.button(format!("{:.0}%", self.zoom * 100.0));
+                                if self.shortcut_hint(response, Action::ZoomReset).clicked() {
+                                    self.perform_action(Action::ZoomReset, frame);
+                                }
                             }
 
 
@@ -979,14 +2969,19 @@ pub mod state_module {
                                 // Color Picker, Size Picker for Brush, Highlight, Erase, Shapes, Text
                                 ui.with_layout(Layout::right_to_left(Align::RIGHT), |ui| {
                                     //SIZE FOR ALL
-                                    if self.drawing_mode != Some(DrawingMode::Crop) && self.drawing_mode != Some(DrawingMode::Erase) && self.drawing_mode != None {
+                                    if self.drawing_mode != Some(DrawingMode::Crop) && self.drawing_mode != Some(DrawingMode::Erase) && self.drawing_mode != Some(DrawingMode::Spotlight) && self.drawing_mode != Some(DrawingMode::Overlay) && self.drawing_mode.is_some() {
                                         //with color picker
                                         let picker = ui.color_edit_button_srgb(&mut self.tool_color).clone();
+                                        let secondary = Color32::from_rgb(self.secondary_color[0], self.secondary_color[1], self.secondary_color[2]);
+                                        let swap = ui.add(Button::new("").fill(secondary).min_size(Vec2::splat(12.0)));
+                                        if self.shortcut_hint(swap, Action::SwapColors).clicked() {
+                                            std::mem::swap(&mut self.tool_color, &mut self.secondary_color);
+                                        }
                                         match self.drawing_mode {
                                             Some(DrawingMode::Paint) => {
                                                 ui.add(Slider::new(&mut self.tool_size, 3.0..=25.0));
                                                 if picker.clicked() {
-                                                    let result = self.hotkey_manager.set_active_shortcuts(ActiveShortcuts::Pause);
+                                                    let result = self.hotkey_manager.set_context(ShortcutContext::Pause);
                                                     self.manage_errors(result);
                                                     self.previous_drawing_mode = Some(DrawingMode::Paint);
                                                     self.drawing_mode = Some(DrawingMode::Pause);
@@ -995,7 +2990,7 @@ pub mod state_module {
                                             Some(DrawingMode::Highlight) => {
                                                 ui.add(Slider::new(&mut self.tool_size, 3.0..=25.0));
                                                 if picker.clicked() {
-                                                    let result = self.hotkey_manager.set_active_shortcuts(ActiveShortcuts::Pause);
+                                                    let result = self.hotkey_manager.set_context(ShortcutContext::Pause);
                                                     self.manage_errors(result);
                                                     self.previous_drawing_mode = Some(DrawingMode::Highlight);
                                                     self.drawing_mode = Some(DrawingMode::Pause);
@@ -1007,7 +3002,7 @@ pub mod state_module {
                                                 if ui.button("\u{2B55}").clicked() { self.shape = Some(Shape::Circle); }
                                                 if ui.button("\u{2197}").clicked() { self.shape = Some(Shape::Arrow); }
                                                 if picker.clicked() {
-                                                    let result = self.hotkey_manager.set_active_shortcuts(ActiveShortcuts::Pause);
+                                                    let result = self.hotkey_manager.set_context(ShortcutContext::Pause);
                                                     self.manage_errors(result);
                                                     self.previous_drawing_mode = Some(DrawingMode::Shape);
                                                     self.drawing_mode = Some(DrawingMode::Pause);
@@ -1015,30 +3010,78 @@ pub mod state_module {
                                             }
                                             Some(DrawingMode::Text) => {
                                                 ui.add(Slider::new(&mut self.tool_size, 3.0..=25.0));
+                                                ui.selectable_value(&mut self.text_font, TextFont::Sans, "Sans");
+                                                ui.selectable_value(&mut self.text_font, TextFont::Monospace, "Mono");
                                                 self.drawing_mode = Some(DrawingMode::Text);
                                                 if picker.clicked() {
-                                                    let result = self.hotkey_manager.set_active_shortcuts(ActiveShortcuts::Pause);
+                                                    let result = self.hotkey_manager.set_context(ShortcutContext::Pause);
                                                     self.manage_errors(result);
                                                     self.previous_drawing_mode = Some(DrawingMode::Text);
                                                     self.drawing_mode = Some(DrawingMode::Pause);
                                                 }
                                             }
+                                            Some(DrawingMode::StepMarker) => {
+                                                let text_picker = ui.color_edit_button_srgb(&mut self.marker_text_color).on_hover_text("Number color");
+                                                ui.add(Slider::new(&mut self.marker_size, 16.0..=80.0));
+                                                if ui.button("\u{25A0}").clicked() { self.marker_shape = MarkerShape::Square; }
+                                                if ui.button("\u{25CF}").clicked() { self.marker_shape = MarkerShape::Circle; }
+                                                ui.checkbox(&mut self.marker_leader, "Leader");
+                                                if picker.clicked() || text_picker.clicked() {
+                                                    let result = self.hotkey_manager.set_context(ShortcutContext::Pause);
+                                                    self.manage_errors(result);
+                                                    self.previous_drawing_mode = Some(DrawingMode::StepMarker);
+                                                    self.drawing_mode = Some(DrawingMode::Pause);
+                                                }
+                                            }
+                                            Some(DrawingMode::Magnify) => {
+                                                ui.add(Slider::new(&mut self.magnify_factor, 2..=8).suffix("x"));
+                                                ui.checkbox(&mut self.magnify_smooth, "Smooth");
+                                                ui.checkbox(&mut self.magnify_connectors, "Connectors");
+                                                if picker.clicked() {
+                                                    let result = self.hotkey_manager.set_context(ShortcutContext::Pause);
+                                                    self.manage_errors(result);
+                                                    self.previous_drawing_mode = Some(DrawingMode::Magnify);
+                                                    self.drawing_mode = Some(DrawingMode::Pause);
+                                                }
+                                            }
                                             Some(DrawingMode::Pause) => {
-                                                if picker.clicked_elsewhere() || ctx.input(|is| is.key_pressed(Key::Escape))
+                                                // the number color picker has to stay on screen while its popup is open
+                                                let mut text_picker_clicked_elsewhere = true;
+                                                if self.previous_drawing_mode == Some(DrawingMode::StepMarker) {
+                                                    text_picker_clicked_elsewhere = ui.color_edit_button_srgb(&mut self.marker_text_color).clicked_elsewhere();
+                                                }
+                                                if (picker.clicked_elsewhere() && text_picker_clicked_elsewhere) || ctx.input(|is| is.key_pressed(Key::Escape))
                                                 {
                                                     self.drawing_mode = self.previous_drawing_mode;
-                                                    let result = self.hotkey_manager.set_active_shortcuts(ActiveShortcuts::ScreenshotDone);
+                                                    let result = self.hotkey_manager.set_context(ShortcutContext::ScreenshotDone);
                                                     self.manage_errors(result);
                                                 }
                                             }
                                             _ => {}
                                         }
                                     } else {
-                                        //without color picker (Crop,Erase,None)
+                                        //without color picker (Crop,Erase,Spotlight,None)
                                         match self.drawing_mode {
                                             Some(DrawingMode::Erase) => {
                                                 ui.add(Slider::new(&mut self.tool_size, 3.0..=25.0));
                                             }
+                                            Some(DrawingMode::Spotlight) => {
+                                                let (mut effect, mut amount) = self.screenshot.get_spotlight_effect();
+                                                let mut changed = ui.add(Slider::new(&mut amount, 0.0..=1.0)).changed();
+                                                changed |= ui.selectable_value(&mut effect, SpotlightEffect::Desaturate, "Desaturate").changed();
+                                                changed |= ui.selectable_value(&mut effect, SpotlightEffect::Darken, "Darken").changed();
+                                                if ui.button("\u{2B2D}").clicked() { self.spotlight_shape = SpotlightShape::Ellipse; }
+                                                if ui.button("\u{25AD}").clicked() { self.spotlight_shape = SpotlightShape::Rectangle; }
+                                                if ui.button("Clear").clicked() {
+                                                    self.push_undo(self.screenshot.clone());
+                                                    self.screenshot.clear_spotlights();
+                                                    changed = true;
+                                                }
+                                                if changed {
+                                                    self.screenshot.set_spotlight_effect(effect, amount);
+                                                    self.convert_image();
+                                                }
+                                            }
                                             _ => {}
                                         }
                                     }
@@ -1049,7 +3092,136 @@ pub mod state_module {
                 });
 
 
+            // crop toolbar, above the footer
+            if self.show_image && self.drawing_mode == Some(DrawingMode::Crop) {
+                TopBottomPanel::bottom("crop_toolbar")
+                    .frame(
+                        Frame {
+                            inner_margin: Margin::same(1.0),
+                            outer_margin: Margin::same(0.0),
+                            fill: ctx.style().visuals.panel_fill,
+                            ..Default::default()
+                        }
+                    )
+                    .resizable(false)
+                    .show(ctx, |ui| {
+                        ui.set_enabled(!self.error_dialog);
+
+                        ui.horizontal(|ui| {
+                            let size = self.image_size();
+                            let ratio = self.crop_ratio();
+                            let rect = self.crop_rect.unwrap_or(Rect::from_min_size(Pos2::ZERO, Vec2::ZERO));
+                            let (mut x, mut y, mut w, mut h) = (rect.min.x, rect.min.y, rect.width(), rect.height());
+                            ui.set_enabled(!self.error_dialog && self.crop_rect.is_some());
+                            ui.label("X");
+                            let mut changed = ui.add(egui::DragValue::new(&mut x).clamp_range(0.0..=size.x).max_decimals(0)).changed();
+                            ui.label("Y");
+                            changed |= ui.add(egui::DragValue::new(&mut y).clamp_range(0.0..=size.y).max_decimals(0)).changed();
+                            ui.label("W");
+                            if ui.add(egui::DragValue::new(&mut w).clamp_range(1.0..=size.x).max_decimals(0)).changed() {
+                                h = ratio.map_or(h, |ratio| w / ratio);
+                                changed = true;
+                            }
+                            ui.label("H");
+                            if ui.add(egui::DragValue::new(&mut h).clamp_range(1.0..=size.y).max_decimals(0)).changed() {
+                                w = ratio.map_or(w, |ratio| h * ratio);
+                                changed = true;
+                            }
+                            if changed {
+                                // shrink to the image keeping the ratio, then move back inside it
+                                let rect = crop_rect_from_anchor(Pos2::ZERO, Pos2::new(w, h), ratio, size);
+                                let min = Pos2::new(x.min(size.x - rect.width()), y.min(size.y - rect.height()));
+                                self.crop_rect = Some(Rect::from_min_size(min, rect.size()));
+                            }
+                            ui.set_enabled(!self.error_dialog);
+
+                            ui.separator();
+                            let aspect = self.crop_aspect;
+                            ComboBox::from_label("Ratio")
+                                .selected_text(self.crop_aspect.to_string())
+                                .show_ui(ui, |ui| {
+                                    for preset in [AspectRatio::Free, AspectRatio::Square, AspectRatio::FourThree, AspectRatio::SixteenNine, AspectRatio::Custom] {
+                                        ui.selectable_value(&mut self.crop_aspect, preset, preset.to_string());
+                                    }
+                                });
+                            let mut ratio_changed = aspect != self.crop_aspect;
+                            if self.crop_aspect == AspectRatio::Custom {
+                                ratio_changed |= ui.add(egui::DragValue::new(&mut self.crop_custom_ratio[0]).clamp_range(1.0..=100.0)).changed();
+                                ui.label(":");
+                                ratio_changed |= ui.add(egui::DragValue::new(&mut self.crop_custom_ratio[1]).clamp_range(1.0..=100.0)).changed();
+                            }
+                            if ratio_changed {
+                                self.fit_crop_to_ratio();
+                            }
+                            ui.checkbox(&mut self.crop_thirds, "Thirds");
+
+                            ui.separator();
+                            if ui.button("Auto-trim").on_hover_text("Select the image without its uniform borders").clicked() {
+                                match self.screenshot.trim_bounds(TRIM_TOLERANCE) {
+                                    Some((x, y, w, h)) => {
+                                        self.crop_rect = Some(Rect::from_min_size(Pos2::new(x as f32, y as f32), Vec2::new(w as f32, h as f32)));
+                                    }
+                                    None => {
+                                        self.manage_errors::<()>(Err(EditError::NothingToTrim.into()));
+                                    }
+                                }
+                            }
+                            if ui.add_enabled(self.crop_rect.is_some(), egui::Button::new("Apply")).clicked() {
+                                self.apply_crop();
+                            }
+                            if ui.button("Cancel").clicked() {
+                                self.crop_rect = None;
+                                self.crop_drag = None;
+                                self.drawing_mode = None;
+                            }
+                        });
+                    });
+            }
+
             //GIVEN A DRAWING MODE EXECUTES THE FUNCTION
+            // HISTORY_PANEL
+            if self.history_panel {
+                egui::SidePanel::left("history_panel")
+                    .resizable(true)
+                    .default_width(230.0)
+                    .show(ctx, |ui| {
+                        ui.set_enabled(!self.error_dialog && !self.screenshot_taken);
+
+                        let mut commands = Vec::new();
+                        let mut thumbnail_loaded = false;
+                        egui::ScrollArea::vertical().show(ui, |ui| {
+                            ui.strong("Recent captures");
+                            if self.history.entries.is_empty() {
+                                ui.label(if !self.history.is_enabled() {
+                                    "The history is off, there is no folder to keep it in"
+                                } else if self.settings.history_limit == 0 {
+                                    "The history is disabled in the settings"
+                                } else {
+                                    "Nothing captured yet"
+                                });
+                            }
+                            for (index, entry) in self.history.entries.clone().iter().enumerate() {
+                                if let Some(command) = self.history_entry_ui(ui, entry, &mut thumbnail_loaded) {
+                                    commands.push((entry.clone(), Some(index), command));
+                                }
+                            }
+                            ui.separator();
+                            ui.strong("Saved screenshots");
+                            for entry in self.history_saved.clone().iter() {
+                                if let Some(command) = self.history_entry_ui(ui, entry, &mut thumbnail_loaded) {
+                                    commands.push((entry.clone(), None, command));
+                                }
+                            }
+                        });
+                        if thumbnail_loaded {
+                            ctx.request_repaint();
+                        }
+                        for (entry, index, command) in commands {
+                            self.run_history_command(&entry, index, command);
+                        }
+                    });
+            }
+
             CentralPanel::default()
                 .frame(Frame::none())
                 .show(ctx, |ui| {
@@ -1077,7 +3249,10 @@ pub mod state_module {
                                 .show(ctx, |ui_window| {
                                     let w = ui_window.add(
                                         TextEdit::multiline(&mut self.text)
-                                            .font(egui::FontId::proportional(self.tool_size))
+                                            .font(match self.text_font {
+                                                TextFont::Sans => egui::FontId::proportional(self.tool_size),
+                                                TextFont::Monospace => egui::FontId::monospace(self.tool_size),
+                                            })
                                             .text_color(Color32::from_rgb(self.tool_color[0], self.tool_color[1], self.tool_color[2]))
                                             .frame(false)
                                     );
@@ -1091,12 +3266,14 @@ pub mod state_module {
                                     } else if enter_pressed {
                                         //print the line
                                         self.text_edit_dialog = false;
-                                        let textbox_pos = self.calculate_texture_coordinates(w.rect.left_top(), ui.available_size(), ctx.used_size(), true).unwrap();
-                                        let x = self.tool_size / values_window.4;
-                                        let y = self.tool_size / values_window.5;
-                                        self.screenshot.draw_text(&self.text, textbox_pos.x.max(0.0), textbox_pos.y.max(0.0), self.tool_color, Scale { x, y });
+                                        if let Some(textbox_pos) = self.calculate_texture_coordinates(w.rect.left_top(), ui.available_size(), ctx.used_size(), true) {
+                                            self.push_undo(self.screenshot.clone());
+                                            let x = self.tool_size / values_window.4;
+                                            let y = self.tool_size / values_window.5;
+                                            self.screenshot.draw_text(&self.text, textbox_pos.x.max(0.0), textbox_pos.y.max(0.0), self.tool_color, Scale { x, y }, self.text_font);
+                                            self.convert_image();
+                                        }
                                         self.text = "".to_string();
-                                        self.convert_image();
                                     } else if exit_pressed {
                                         //exit from the line
                                         self.text_edit_dialog = false;
@@ -1109,7 +3286,29 @@ pub mod state_module {
                         if self.show_image {
                             let available = ui.available_size();
                             let mut my_image = MyImage::new();
-                            my_image.ui_resize(ui, self.color_image.clone());
+                            my_image.ui_resize(ui, self.color_image.clone(), self.image_rect(available, ctx.used_size()));
+
+                            // the middle button drags a zoomed image around
+                            let pan = ctx.input(|is| if is.pointer.middle_down() { is.pointer.delta() } else { Vec2::ZERO });
+                            self.pan += pan;
+
+                            // every press of a drawing tool on the image is an edit which can be undone
+                            let pressed = ctx.input(|is| is.pointer.any_pressed().then(|| is.pointer.interact_pos()).flatten());
+                            if let Some(pos) = pressed {
+                                let editing_tool = match self.drawing_mode {
+                                    Some(DrawingMode::Paint | DrawingMode::Highlight | DrawingMode::Erase | DrawingMode::Shape | DrawingMode::StepMarker | DrawingMode::Spotlight) => true,
+                                    Some(DrawingMode::Magnify) => self.magnify_source.is_some(),
+                                    _ => false,
+                                };
+                                if editing_tool && self.calculate_texture_coordinates(pos, available, ctx.used_size(), false).is_some() {
+                                    let mut snapshot = self.screenshot.clone();
+                                    if self.drawing_mode == Some(DrawingMode::Shape) || self.drawing_mode == Some(DrawingMode::Magnify) {
+                                        // the preview is not part of the edit
+                                        snapshot.rollback_changes();
+                                    }
+                                    self.push_undo(snapshot);
+                                }
+                            }
                             // drawing
                             match self.drawing_mode {
                                 Some(DrawingMode::Paint) => {
@@ -1157,27 +3356,59 @@ pub mod state_module {
                                     }
                                 }
                                 Some(DrawingMode::Crop) => {
-                                    let coordinates = self.draw_rectangle(ctx, available, 2.0, [255, 255, 255, 255]);
-                                    if coordinates.is_some() {
-                                        let coordinates = coordinates.unwrap();
-                                        let height = (coordinates.0.1 - coordinates.1.1).abs() as i32;
-                                        let width = (coordinates.0.0 - coordinates.1.0).abs() as i32;
-                                        let min_x = cmp::min(coordinates.0.0 as u32, coordinates.1.0 as u32);
-                                        let min_y = cmp::min(coordinates.0.1 as u32, coordinates.1.1 as u32);
-                                        let result = self.screenshot.resize_image(min_x + 2, min_y + 2, height - 4, width - 4);
-                                        if self.manage_errors(result).is_none() {
-                                            self.screenshot = self.crop_screenshot_tmp.clone();
+                                    match self.draw_crop(ctx, available) {
+                                        Some(cursor) => {
+                                            ctx.set_cursor_icon(cursor);
+                                        }
+                                        None => {
+                                            ctx.set_cursor_icon(CursorIcon::Default);
+                                        }
+                                    }
+                                    self.paint_crop_overlay(ui.painter(), available, ctx.used_size());
+                                }
+                                Some(DrawingMode::Overlay) => {
+                                    let cursor = self.draw_overlay(ctx, available);
+                                    ctx.set_cursor_icon(cursor.unwrap_or(CursorIcon::Default));
+                                    self.paint_overlay(ctx, ui.painter(), available);
+                                }
+                                Some(DrawingMode::StepMarker) => {
+                                    match self.draw_step_marker(ctx, available) {
+                                        true => {
+                                            ctx.set_cursor_icon(CursorIcon::Crosshair);
+                                        }
+                                        false => {
+                                            ctx.set_cursor_icon(CursorIcon::Default);
+                                        }
+                                    }
+                                }
+                                Some(DrawingMode::Magnify) => {
+                                    match self.draw_magnify(ctx, available, [self.tool_color[0], self.tool_color[1], self.tool_color[2], 255]) {
+                                        true => {
+                                            ctx.set_cursor_icon(CursorIcon::ZoomIn);
+                                        }
+                                        false => {
+                                            ctx.set_cursor_icon(CursorIcon::Default);
+                                        }
+                                    }
+                                }
+                                Some(DrawingMode::Spotlight) => {
+                                    match self.draw_spotlight(ctx, available) {
+                                        true => {
+                                            ctx.set_cursor_icon(CursorIcon::Crosshair);
+                                        }
+                                        false => {
+                                            ctx.set_cursor_icon(CursorIcon::Default);
                                         }
-                                        self.convert_image();
                                     }
                                 }
                                 Some(DrawingMode::Text) => {
-                                    ctx.input(|is| {
-                                        if is.pointer.any_down() && !self.text_edit_dialog && self.calculate_texture_coordinates(is.pointer.interact_pos().unwrap(), available, ctx.used_size(), false).is_some() {
-                                            self.text_edit_dialog_position = is.pointer.interact_pos().unwrap();
+                                    let pressed = ctx.input(|is| is.pointer.any_down().then(|| is.pointer.interact_pos()).flatten());
+                                    if let Some(pos) = pressed {
+                                        if !self.text_edit_dialog && self.calculate_texture_coordinates(pos, available, ctx.used_size(), false).is_some() {
+                                            self.text_edit_dialog_position = pos;
                                             self.text_edit_dialog = true;
                                         }
-                                    });
+                                    }
                                 }
                                 _ => {}
                             }
@@ -1193,7 +3424,7 @@ pub mod state_module {
     }
 
     impl MyImage {
-        pub fn ui_resize(&mut self, ui: &mut egui::Ui, image: ColorImage) {
+        pub fn ui_resize(&mut self, ui: &mut egui::Ui, image: ColorImage, rect: Rect) {
             let texture: &egui::TextureHandle = self.texture.get_or_insert_with(|| {
                 // Load the texture only once.
                 ui.ctx().load_texture(
@@ -1203,17 +3434,9 @@ pub mod state_module {
                 )
             });
 
-            let available = ui.available_size();
-            let w = image.width() as f32;
-            let h = image.height() as f32;
-            let w_window = available.x;
-            let h_window = available.y;
-            // gives the min between the height of the window and the height of the image scaled to the width of the window
-            let height = h_window.min(w_window * h / w);
-            let width = height * w / h;
-            let fixed_dimensions = Vec2 { x: width, y: height };
-            // Show the image:
-            ui.image(texture, fixed_dimensions);
+            // Show the image where ScreenshotStr::image_rect placed it
+            let uv = Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0));
+            ui.painter().image(texture.id(), rect, uv, Color32::WHITE);
         }
 
         pub fn new() -> Self {
@@ -1221,17 +3444,338 @@ pub mod state_module {
         }
     }
 
+    /// Handles of the crop rectangle as (hx, hy) where -1, 0 and 1 stand for the left (top), middle
+    /// and right (bottom) position
+    const CROP_HANDLES: [(i8, i8); 8] = [(-1, -1), (0, -1), (1, -1), (1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0)];
+
+    fn crop_handle_pos(rect: Rect, hx: i8, hy: i8) -> Pos2 {
+        let x = match hx {
+            -1 => rect.min.x,
+            0 => rect.center().x,
+            _ => rect.max.x,
+        };
+        let y = match hy {
+            -1 => rect.min.y,
+            0 => rect.center().y,
+            _ => rect.max.y,
+        };
+        Pos2::new(x, y)
+    }
+
+    fn crop_handle_at(rect: Rect, p: Pos2, tolerance: f32) -> Option<(i8, i8)> {
+        CROP_HANDLES.iter().copied().find(|&(hx, hy)| crop_handle_pos(rect, hx, hy).distance(p) <= tolerance)
+    }
+
+    /// Builds the rectangle between a fixed corner and the pointer, forcing the given width / height
+    /// ratio and shrinking it (keeping the ratio) so that it does not exceed an image of the given size
+    fn crop_rect_from_anchor(anchor: Pos2, pointer: Pos2, ratio: Option<f32>, size: Vec2) -> Rect {
+        let (mut w, mut h) = (pointer.x - anchor.x, pointer.y - anchor.y);
+        if let Some(ratio) = ratio {
+            if w.abs() / ratio > h.abs() {
+                h = (w.abs() / ratio).copysign(h);
+            } else {
+                w = (h.abs() * ratio).copysign(w);
+            }
+        }
+        let max_w = if w >= 0.0 { size.x - anchor.x } else { anchor.x };
+        let max_h = if h >= 0.0 { size.y - anchor.y } else { anchor.y };
+        let factor = 1f32.min(max_w / w.abs().max(f32::EPSILON)).min(max_h / h.abs().max(f32::EPSILON));
+        Rect::from_two_pos(anchor, anchor + Vec2::new(w * factor, h * factor))
+    }
+
+    fn filter_name(filter: FilterType) -> &'static str {
+        match filter {
+            FilterType::Nearest => "Nearest",
+            FilterType::Triangle => "Bilinear",
+            FilterType::CatmullRom => "Bicubic",
+            FilterType::Gaussian => "Gaussian",
+            FilterType::Lanczos3 => "Lanczos",
+        }
+    }
+
+    /// Fields of a capture preset in the settings, returns whether one of them changed
+    fn preset_editor(ui: &mut egui::Ui, index: usize, preset: &mut CapturePreset) -> bool {
+        let mut changed = false;
+        Grid::new(("preset_grid", index)).num_columns(2).show(ui, |ui| {
+            ui.label("Name");
+            changed |= ui.text_edit_singleline(&mut preset.name).changed();
+            ui.end_row();
+
+            ui.label("Screen");
+            changed |= ui.add(egui::DragValue::new(&mut preset.screen).clamp_range(0..=get_screens().len().saturating_sub(1))).changed();
+            ui.end_row();
+
+            ui.label("Delay");
+            changed |= ui.add(egui::DragValue::new(&mut preset.delay).clamp_range(0..=60).suffix(" s")).changed();
+            ui.end_row();
+
+            ui.label("Region");
+            ui.horizontal(|ui| {
+                let mut whole = preset.region.is_none();
+                if ui.checkbox(&mut whole, "Whole screen").changed() {
+                    preset.region = if whole { None } else { Some([0, 0, 800, 600]) };
+                    changed = true;
+                }
+                if let Some(region) = preset.region.as_mut() {
+                    for (value, prefix) in region.iter_mut().zip(["x ", "y ", "w ", "h "]) {
+                        changed |= ui.add(egui::DragValue::new(value).prefix(prefix)).changed();
+                    }
+                }
+            });
+            ui.end_row();
+
+            ui.label("Format");
+            ui.horizontal(|ui| {
+                for (format, name) in [("png", "PNG"), ("jpg", "JPG"), ("gif", "GIF")] {
+                    if ui.selectable_label(preset.format == format, name).clicked() {
+                        preset.format = format.to_string();
+                        changed = true;
+                    }
+                }
+            });
+            ui.end_row();
+
+            ui.label("Border");
+            ui.horizontal(|ui| {
+                changed |= ui.add(egui::DragValue::new(&mut preset.border).clamp_range(0..=200).suffix(" px")).changed();
+                changed |= ui.color_edit_button_srgb(&mut preset.border_color).changed();
+            });
+            ui.end_row();
+
+            ui.label("Destination");
+            changed |= ui.add(TextEdit::singleline(&mut preset.destination).hint_text("Default path")).changed();
+            ui.end_row();
+
+            ui.label("Then");
+            ui.horizontal(|ui| {
+                changed |= ui.checkbox(&mut preset.save, "Save").changed();
+                changed |= ui.checkbox(&mut preset.copy_to_clipboard, "Copy to clipboard").changed();
+                changed |= ui.checkbox(&mut preset.open_in_editor, "Open in editor").changed();
+            });
+            ui.end_row();
+        });
+        changed
+    }
+
+    /// The settings set by the environment or the command line, as listed in the settings window
+    fn overridden(sources: Sources) -> Vec<String> {
+        sources.into_iter()
+            .filter(|(_, source)| matches!(source, Source::Environment(_) | Source::CommandLine(_)))
+            .map(|(key, source)| format!("{} ({})", key, source))
+            .collect()
+    }
+
+    /// The metadata saved with a screenshot, none when the settings strip it
+    fn save_metadata(settings: &Settings, screenshot: &Screenshot, caption: &Caption) -> Option<Metadata> {
+        (!settings.strip_metadata).then(|| screenshot.metadata(caption))
+    }
+
+    /// Crops a capture to the region of the preset and adds its border, then saves and copies it
+    /// as the preset says
+    fn apply_preset(screenshot: &mut Screenshot, preset: &CapturePreset, settings: &Settings) -> Result<(), RustyCaptureError> {
+        if let Some([x, y, width, height]) = preset.region {
+            let (x, y) = (x.min(screenshot.get_width().saturating_sub(1)), y.min(screenshot.get_height().saturating_sub(1)));
+            let width = width.min(screenshot.get_width().saturating_sub(x));
+            let height = height.min(screenshot.get_height().saturating_sub(y));
+            screenshot.resize_image(x, y, height as i32, width as i32)?;
+        }
+        if preset.border > 0 {
+            let color = [preset.border_color[0], preset.border_color[1], preset.border_color[2], 255];
+            screenshot.pad(preset.border, preset.border, preset.border, preset.border, color)?;
+        }
+        let mut saved = None;
+        if preset.save {
+            let format = ImageFormat::from_extension(&preset.format).unwrap_or(ImageFormat::Png);
+            let default_dir = settings.get_path();
+            let dir = if preset.destination.is_empty() { &default_dir } else { &preset.destination };
+            let metadata = save_metadata(settings, screenshot, &Caption::default());
+            saved = Some(screenshot.save_image(&PathBuf::from(dir), format, metadata.as_ref())?);
+        }
+        if preset.copy_to_clipboard {
+            screenshot.save_to_clipboard(settings.clipboard_mode, saved.as_deref())?;
+        }
+        Ok(())
+    }
+
+    /// Title of the tab of an image opened from a file
+    fn file_title(path: &Path) -> String {
+        path.file_name().map_or_else(|| path.to_string_lossy().into_owned(), |name| name.to_string_lossy().into_owned())
+    }
+
+    /// Style of the editor state used by a tool, if the tool has one
+    fn tool_style(editor: &mut EditorState, mode: Option<DrawingMode>) -> Option<&mut ToolStyle> {
+        match mode? {
+            DrawingMode::Paint => Some(&mut editor.pen),
+            DrawingMode::Highlight => Some(&mut editor.highlighter),
+            DrawingMode::Erase => Some(&mut editor.eraser),
+            DrawingMode::Shape => Some(&mut editor.shapes),
+            DrawingMode::Text => Some(&mut editor.text),
+            DrawingMode::StepMarker => Some(&mut editor.step_marker),
+            DrawingMode::Magnify => Some(&mut editor.magnify),
+            _ => None,
+        }
+    }
+
+    /// Converts an accelerator to the shortcut egui matches against the window input, if egui
+    /// knows its key
+    fn egui_shortcut(accelerator: Accelerator) -> Option<egui::KeyboardShortcut> {
+        let modifiers = egui::Modifiers {
+            alt: accelerator.modifiers.contains(Modifiers::ALT),
+            ctrl: accelerator.modifiers.contains(Modifiers::CONTROL),
+            shift: accelerator.modifiers.contains(Modifiers::SHIFT),
+            mac_cmd: accelerator.modifiers.contains(Modifiers::SUPER),
+            command: accelerator.modifiers.contains(Modifiers::CONTROL),
+        };
+        let key = match accelerator.key {
+            Code::KeyA => Key::A,
+            Code::KeyB => Key::B,
+            Code::KeyC => Key::C,
+            Code::KeyD => Key::D,
+            Code::KeyE => Key::E,
+            Code::KeyF => Key::F,
+            Code::KeyG => Key::G,
+            Code::KeyH => Key::H,
+            Code::KeyI => Key::I,
+            Code::KeyJ => Key::J,
+            Code::KeyK => Key::K,
+            Code::KeyL => Key::L,
+            Code::KeyM => Key::M,
+            Code::KeyN => Key::N,
+            Code::KeyO => Key::O,
+            Code::KeyP => Key::P,
+            Code::KeyQ => Key::Q,
+            Code::KeyR => Key::R,
+            Code::KeyS => Key::S,
+            Code::KeyT => Key::T,
+            Code::KeyU => Key::U,
+            Code::KeyV => Key::V,
+            Code::KeyW => Key::W,
+            Code::KeyX => Key::X,
+            Code::KeyY => Key::Y,
+            Code::KeyZ => Key::Z,
+            Code::Digit0 | Code::Numpad0 => Key::Num0,
+            Code::Digit1 | Code::Numpad1 => Key::Num1,
+            Code::Digit2 | Code::Numpad2 => Key::Num2,
+            Code::Digit3 | Code::Numpad3 => Key::Num3,
+            Code::Digit4 | Code::Numpad4 => Key::Num4,
+            Code::Digit5 | Code::Numpad5 => Key::Num5,
+            Code::Digit6 | Code::Numpad6 => Key::Num6,
+            Code::Digit7 | Code::Numpad7 => Key::Num7,
+            Code::Digit8 | Code::Numpad8 => Key::Num8,
+            Code::Digit9 | Code::Numpad9 => Key::Num9,
+            Code::F1 => Key::F1,
+            Code::F2 => Key::F2,
+            Code::F3 => Key::F3,
+            Code::F4 => Key::F4,
+            Code::F5 => Key::F5,
+            Code::F6 => Key::F6,
+            Code::F7 => Key::F7,
+            Code::F8 => Key::F8,
+            Code::F9 => Key::F9,
+            Code::F10 => Key::F10,
+            Code::F11 => Key::F11,
+            Code::F12 => Key::F12,
+            Code::F13 => Key::F13,
+            Code::F14 => Key::F14,
+            Code::F15 => Key::F15,
+            Code::F16 => Key::F16,
+            Code::F17 => Key::F17,
+            Code::F18 => Key::F18,
+            Code::F19 => Key::F19,
+            Code::F20 => Key::F20,
+            Code::ArrowDown => Key::ArrowDown,
+            Code::ArrowLeft => Key::ArrowLeft,
+            Code::ArrowRight => Key::ArrowRight,
+            Code::ArrowUp => Key::ArrowUp,
+            Code::Escape => Key::Escape,
+            Code::Tab => Key::Tab,
+            Code::Backspace => Key::Backspace,
+            Code::Enter | Code::NumpadEnter => Key::Enter,
+            Code::Space => Key::Space,
+            Code::Insert => Key::Insert,
+            Code::Delete => Key::Delete,
+            Code::Home => Key::Home,
+            Code::End => Key::End,
+            Code::PageUp => Key::PageUp,
+            Code::PageDown => Key::PageDown,
+            Code::Minus | Code::NumpadSubtract => Key::Minus,
+            Code::Equal | Code::NumpadAdd => Key::PlusEquals,
+            _ => return None,
+        };
+        Some(egui::KeyboardShortcut::new(modifiers, key))
+    }
+
+    /// Writes a combination pressed in the window the same way an Accelerator is parsed
+    fn accelerator_name(modifiers: egui::Modifiers, key: Key) -> String {
+        let mut name = String::new();
+        for (pressed, modifier) in [(modifiers.ctrl, "Ctrl"), (modifiers.alt, "Alt"), (modifiers.shift, "Shift"), (modifiers.mac_cmd, "Super")] {
+            if pressed {
+                name.push_str(modifier);
+                name.push('+');
+            }
+        }
+        name.push_str(match key {
+            Key::PlusEquals => "Equal",
+            _ => key.name(),
+        });
+        name
+    }
+
+    /// The connected screens, none if they cannot be listed
     pub fn get_screens() -> Vec<Screen> {
-        let screens = Screen::all().unwrap();
-        screens
+        Screen::all().unwrap_or_default()
     }
 
-    pub fn take_screenshot(timer: Duration, screen: usize) -> Screenshot {
-        let screens = Screen::all().unwrap();
-        let screen = screens[screen].clone();
+    pub fn take_screenshot(timer: Duration, screen: usize) -> Result<Screenshot, RustyCaptureError> {
+        let screens = Screen::all().map_err(|e| CaptureError::ScreenList(e.into()))?;
+        let count = screens.len();
+        let index = screen;
+        let screen = screens.into_iter().nth(screen)
+            .ok_or(if count == 0 { CaptureError::NoScreens } else { CaptureError::MissingScreen { screen, count } })?;
         //screenshot after delay
-        let ss1 = Screenshot::screenshot_after_delay(timer, screen).unwrap();
-        //save image to clipboard
-        ss1
+        let mut screenshot = Screenshot::screenshot_after_delay(timer, screen)?;
+        screenshot.set_screen(index);
+        Ok(screenshot)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use eframe::epaint::{Pos2, Rect, Vec2};
+        use super::crop_rect_from_anchor;
+
+        fn rect(x0: f32, y0: f32, x1: f32, y1: f32) -> Rect {
+            Rect::from_min_max(Pos2::new(x0, y0), Pos2::new(x1, y1))
+        }
+
+        #[test]
+        fn free_crop_follows_the_pointer() {
+            let crop = crop_rect_from_anchor(Pos2::new(10.0, 10.0), Pos2::new(60.0, 30.0), None, Vec2::new(100.0, 100.0));
+            assert_eq!(crop, rect(10.0, 10.0, 60.0, 30.0));
+        }
+
+        #[test]
+        fn free_crop_is_shrunk_to_stay_in_the_image() {
+            let crop = crop_rect_from_anchor(Pos2::new(10.0, 10.0), Pos2::new(190.0, 30.0), None, Vec2::new(100.0, 100.0));
+            assert_eq!(crop, rect(10.0, 10.0, 100.0, 20.0));
+        }
+
+        #[test]
+        fn locked_ratio_follows_the_longer_side() {
+            let crop = crop_rect_from_anchor(Pos2::new(10.0, 10.0), Pos2::new(50.0, 100.0), Some(2.0), Vec2::new(200.0, 200.0));
+            assert_eq!(crop, rect(10.0, 10.0, 190.0, 100.0));
+        }
+
+        #[test]
+        fn locked_ratio_shrinks_to_fit() {
+            let crop = crop_rect_from_anchor(Pos2::new(10.0, 10.0), Pos2::new(50.0, 100.0), Some(2.0), Vec2::new(100.0, 100.0));
+            assert_eq!(crop, rect(10.0, 10.0, 100.0, 55.0));
+            assert_eq!(crop.width() / crop.height(), 2.0);
+        }
+
+        #[test]
+        fn locked_ratio_towards_the_top_left() {
+            let crop = crop_rect_from_anchor(Pos2::new(50.0, 50.0), Pos2::new(0.0, 40.0), Some(1.0), Vec2::new(100.0, 100.0));
+            assert_eq!(crop, rect(0.0, 0.0, 50.0, 50.0));
+        }
     }
 }
\ No newline at end of file